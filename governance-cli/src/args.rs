@@ -4,26 +4,307 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 pub struct Args {
-    /// Path of the JSON file to be processed
-    pub json_path: PathBuf,
-
     /// Directory to store user data [default: `$HOME/.dusk/rusk-wallet`]
     #[clap(short, long)]
     pub profile: PathBuf,
 
-    /// Set the password for wallet's creation
-    #[clap(long, env = "RUSK_WALLET_PWD")]
-    pub password: String,
+    /// Network of the config to use [default: the top level addresses]
+    #[clap(short, long, global = true, env = "GOV_NETWORK")]
+    pub network: Option<String>,
+
+    /// Name of the production network to send to, instead of typing it
+    #[clap(long, global = true)]
+    pub confirm_network: Option<String>,
+
+    /// Format of the log written to stderr
+    #[clap(long, value_enum, global = true, default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Write the metrics of the run to this file, for the textfile collector
+    #[clap(long, global = true)]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Serve the metrics over HTTP on this address while running, e.g.
+    /// `127.0.0.1:9898`
+    #[clap(long, global = true)]
+    pub metrics_addr: Option<SocketAddr>,
+
+    #[command(flatten)]
+    pub overrides: Overrides,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Config fields replaced from the command line, after the `GOV_*`
+/// environment variables
+#[derive(clap::Args, Debug)]
+pub struct Overrides {
+    /// Address of the rusk node
+    #[clap(long, global = true)]
+    pub rusk_address: Option<String>,
+
+    /// Address of the prover
+    #[clap(long, global = true)]
+    pub prover_address: Option<String>,
+
+    /// Address of the GraphQL endpoint
+    #[clap(long, global = true)]
+    pub graphql_address: Option<String>,
+
+    /// Gas limit of the transactions
+    #[clap(long, global = true)]
+    pub gas_limit: Option<String>,
+
+    /// Gas price of the transactions
+    #[clap(long, global = true)]
+    pub gas_price: Option<String>,
+
+    /// Any other field, as `field=value`
+    #[clap(long = "set", global = true, value_name = "FIELD=VALUE")]
+    pub fields: Vec<String>,
+}
+
+impl Overrides {
+    /// Fields and values to set, in the order they apply
+    pub fn fields(&self) -> anyhow::Result<Vec<(&str, &str)>> {
+        let mut fields = vec![];
+        for field in &self.fields {
+            let (name, value) = field.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("Expected field=value: {field}")
+            })?;
+            fields.push((name, value));
+        }
+
+        let named = [
+            ("rusk_address", &self.rusk_address),
+            ("prover_address", &self.prover_address),
+            ("graphql_address", &self.graphql_address),
+            ("gas_limit", &self.gas_limit),
+            ("gas_price", &self.gas_price),
+        ];
+        for (name, value) in named {
+            if let Some(value) = value {
+                fields.push((name, value.as_str()));
+            }
+        }
+
+        Ok(fields)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Parse and check the input file only
+    Validate(Input),
+
+    /// Print the transfer and fee batches of every security
+    Plan(Input),
+
+    /// Sign the input file and send it to the blockchain
+    Send {
+        #[command(flatten)]
+        input: Input,
+
+        /// Set the password for wallet's creation
+        #[clap(long, env = "RUSK_WALLET_PWD")]
+        password: String,
+
+        /// Print the signed payloads instead of sending them to the
+        /// blockchain
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Write a receipt per batch to this JSON or CSV file
+        #[clap(long)]
+        receipt: Option<PathBuf>,
+
+        /// Send batches already confirmed by an earlier run again
+        #[clap(long)]
+        resend: bool,
+    },
+
+    /// Write the unsigned payloads of the input file, to be signed offline
+    Export {
+        #[command(flatten)]
+        input: Input,
+
+        /// Path of the offline file to write
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+
+    /// Sign the payloads of an offline file, without connecting to the
+    /// network
+    Sign {
+        /// Path of the offline file
+        file: PathBuf,
+
+        /// Path of the signed file to write [default: the offline file]
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        /// Set the password for wallet's creation
+        #[clap(long, env = "RUSK_WALLET_PWD")]
+        password: String,
+    },
+
+    /// Send the payloads of a signed offline file to the blockchain
+    Submit {
+        /// Path of the signed offline file
+        file: PathBuf,
+
+        /// Base58 encoded BLS public key of the provisioner that signed the
+        /// file, replaces `provisioner_key` of the config
+        #[clap(long)]
+        signer: Option<String>,
 
-    /// Use current timestamp instead of the one specified in the input file
+        /// Wallet paying the gas, in place of the one of the profile. It
+        /// doesn't need to hold the provisioner keys
+        #[clap(long)]
+        gas_wallet: Option<PathBuf>,
+
+        /// Set the password of the wallet paying the gas
+        #[clap(long, env = "RUSK_WALLET_PWD")]
+        password: String,
+
+        /// Write a receipt per batch to this JSON or CSV file
+        #[clap(long)]
+        receipt: Option<PathBuf>,
+
+        /// Send batches already confirmed by an earlier run again
+        #[clap(long)]
+        resend: bool,
+    },
+
+    /// Decode a signed payload and check its signature and seed
+    Verify {
+        /// Hex encoded signed payload, as printed by `send --dry-run`
+        payload: String,
+
+        /// bs58 encoded BLS public key of the provisioner
+        #[clap(long)]
+        key: String,
+    },
+
+    /// Send the JSON and CSV files dropped in a directory, moving each one to
+    /// `processed/` or `failed/` with its receipts
+    Watch {
+        /// Directory to watch
+        dir: PathBuf,
+
+        /// Set the password for wallet's creation
+        #[clap(long, env = "RUSK_WALLET_PWD")]
+        password: String,
+
+        /// Seconds between two scans of the directory
+        #[clap(long, default_value_t = 10)]
+        interval: u64,
+
+        /// Reject inputs leading to negative balances, for exports starting
+        /// from the opening of the accounts
+        #[clap(long)]
+        check_ledger: bool,
+    },
+
+    /// Check the hash chain and the signatures of the audit log of the
+    /// profile, and that it still holds the last entry recorded for the
+    /// network
+    Audit,
+
+    /// Print the config in use, after the overrides, with credentials hidden
+    Config,
+
+    /// Query the confirmation status of a transaction
+    Status {
+        /// Hash of the transaction
+        tx_id: String,
+    },
+}
+
+impl Command {
+    /// Input file of the command, if it reads one
+    pub fn input(&self) -> Option<&Input> {
+        match self {
+            Command::Validate(input)
+            | Command::Plan(input)
+            | Command::Send { input, .. }
+            | Command::Export { input, .. } => Some(input),
+            _ => None,
+        }
+    }
+}
+
+/// Input file and how to read it
+#[derive(clap::Args, Debug)]
+pub struct Input {
+    /// Path of the JSON or CSV file to be processed
+    pub json_path: PathBuf,
+
+    /// Format of the input file [default: inferred from the file extension]
+    #[clap(long, value_enum)]
+    pub format: Option<Format>,
+
+    /// Use current timestamp instead of the one specified in the input file.
+    /// Refused when transfers of the input were already sent, as the
+    /// duplicates can't be detected.
     #[clap(long)]
     pub now: bool,
+
+    /// Use `--now` even when transfers of the input were already sent, e.g.
+    /// recurring transfers of the same amount
+    #[clap(long, requires = "now")]
+    pub allow_now: bool,
+
+    /// Reject inputs leading to negative balances, for exports starting
+    /// from the opening of the accounts. Otherwise they are only logged
+    #[clap(long)]
+    pub check_ledger: bool,
+
+    /// Send only the events occurring at or after this RFC 3339 time
+    #[clap(long)]
+    pub from: Option<DateTime<Utc>>,
+
+    /// Send only the events occurring before this RFC 3339 time
+    #[clap(long)]
+    pub to: Option<DateTime<Utc>>,
+
+    /// Send the transfers of every calendar day (UTC) in separate batches
+    #[clap(long, conflicts_with = "now")]
+    pub by_day: bool,
+}
+
+/// Formats of the log
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Supported input formats
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// Guess the format from the file extension, JSON being the default
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(OsStr::to_str) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Json,
+        }
+    }
 }
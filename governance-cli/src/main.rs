@@ -5,47 +5,564 @@
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
 mod args;
+mod watch;
 
-use crate::args::Args;
+use crate::args::{Args, Command, Format, Input, LogFormat};
+use crate::watch::Watch;
 
-use std::error::Error;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fs;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use clap::Parser;
+use dusk_bls12_381_sign::PublicKey as BlsPublicKey;
+use dusk_bytes::Serializable;
 use dusk_wallet::WalletPath;
 use governance_core::prelude::*;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use toml_base_config::BaseConfig;
 use tracing::Level;
 
+/// Audit log of the payloads broadcast, in the profile directory
+const AUDIT_LOG: &str = "audit.log";
+
+/// Time a metrics client has to send its request and read the reply
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> anyhow::Result<()> {
+    let cli = Args::parse();
+
     let subscriber = tracing_subscriber::fmt::Subscriber::builder()
         .with_max_level(Level::INFO)
         .with_writer(std::io::stderr);
-    tracing::subscriber::set_global_default(subscriber.finish())?;
+    match cli.log_format {
+        LogFormat::Text => {
+            tracing::subscriber::set_global_default(subscriber.finish())?
+        }
+        LogFormat::Json => {
+            tracing::subscriber::set_global_default(subscriber.json().finish())?
+        }
+    }
 
-    let cli = Args::parse();
+    let config_path = cli.profile.as_path().join("gov_config.toml");
+    let mut config = Config::load_path(config_path)?;
+    if let Some(network) = &cli.network {
+        config.select_network(network)?;
+    }
+    config.apply_env(std::env::vars())?;
+    for (field, value) in cli.overrides.fields()? {
+        config.set(field, value)?;
+    }
+    if matches!(cli.command.input(), Some(input) if input.by_day) {
+        config.batch_by_day = true;
+    }
+
+    let metrics = Metrics::new();
+    if let Some(addr) = cli.metrics_addr {
+        serve_metrics(addr, metrics.clone()).await?;
+    }
+
+    match cli.command {
+        Command::Validate(input) => {
+            let (ledger, data) = parse(&input, &config)?;
+            let batches =
+                data.into_batches(&config.registry()?, &config.batch_limits())?;
+            let transfers: usize =
+                batches.iter().map(|batch| batch.transfers.len()).sum();
+
+            println!(
+                "{} is valid: {} batch(es), {transfers} transfer(s)",
+                input.json_path.display(),
+                batches.len()
+            );
+
+            for issue in ledger.issues() {
+                println!("  {issue}");
+            }
+            for (name, account) in ledger.accounts() {
+                for (security, balance) in &account.balances {
+                    println!("{name}: {security} {balance}");
+                }
+                for (security, reserved) in &account.reserved {
+                    if !reserved.is_zero() {
+                        println!("{name}: {security} {reserved} reserved");
+                    }
+                }
+            }
+        }
+        Command::Plan(input) => {
+            let (_, data) = parse(&input, &config)?;
+            for batch in
+                data.into_batches(&config.registry()?, &config.batch_limits())?
+            {
+                print_batch(&batch);
+            }
+        }
+        Command::Send {
+            input,
+            password,
+            dry_run,
+            receipt,
+            resend,
+        } => {
+            let (_, data) = parse(&input, &config)?;
+            if input.now && !input.allow_now && !dry_run {
+                let batches = data.clone().into_batches(
+                    &config.registry()?,
+                    &config.batch_limits(),
+                )?;
+                check_now(&cli.profile, &config, &batches)?;
+            }
+            let wallet = secure_wallet(&cli.profile, password)?;
+            if !dry_run {
+                confirm_network(&config, cli.confirm_network.as_deref())?;
+            }
+            let contract =
+                governance(&cli.profile, wallet, config, &metrics, resend);
 
-    let ts_override = cli.now.then(|| {
+            if dry_run {
+                print_dry_run(contract, data)?;
+            } else {
+                let res = contract.send_data(data).await;
+                write_receipts(receipt, &res)?;
+                write_metrics(cli.metrics_file, &metrics)?;
+                res?;
+            }
+        }
+        Command::Export { input, output } => {
+            let (_, data) = parse(&input, &config)?;
+            let batches =
+                data.into_batches(&config.registry()?, &config.batch_limits())?;
+            if input.now && !input.allow_now {
+                check_now(&cli.profile, &config, &batches)?;
+            }
+            let file = OfflineFile::from_batches(batches);
+
+            file.save(&output)?;
+            println!(
+                "{} unsigned batch(es) written to {}",
+                file.batches.len(),
+                output.display()
+            );
+        }
+        Command::Sign {
+            file,
+            output,
+            password,
+        } => {
+            let mut offline = OfflineFile::load(&file)?;
+            let wallet = secure_wallet(&cli.profile, password)?;
+            Governance::from_config(wallet, config).sign(&mut offline)?;
+
+            let output = output.unwrap_or(file);
+            offline.save(&output)?;
+            println!(
+                "{} batch(es) signed in {}",
+                offline.batches.len(),
+                output.display()
+            );
+        }
+        Command::Submit {
+            file,
+            signer,
+            gas_wallet,
+            password,
+            receipt,
+            resend,
+        } => {
+            let offline = OfflineFile::load(file)?;
+            let signer = signer
+                .or_else(|| config.provisioner_key.clone())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown provisioner key, set `provisioner_key` \
+                         or pass --signer"
+                    )
+                })?;
+            let signer = public_key(&signer)?;
+            let mut wallet = secure_wallet(&cli.profile, password)?;
+            if let Some(path) = gas_wallet {
+                wallet.path = WalletPath::from(path);
+            }
+            confirm_network(&config, cli.confirm_network.as_deref())?;
+            let contract =
+                governance(&cli.profile, wallet, config, &metrics, resend);
+
+            let res = contract.submit(offline, &signer).await;
+            write_receipts(receipt, &res)?;
+            write_metrics(cli.metrics_file, &metrics)?;
+            res?;
+        }
+        Command::Verify { payload, key } => {
+            let bytes = hex::decode(payload.trim())?;
+            let key = public_key(&key)?;
+
+            let VerifiedPayload {
+                seed,
+                op,
+                kind,
+                transfers,
+            } = verify_payload(&bytes, &key)?;
+            let kind = kind.map_or(String::from("unknown"), |k| k.to_string());
+
+            println!(
+                "valid signature, {kind} (op {op:#04x}): {} transfer(s), seed {}",
+                transfers.len(),
+                hex::encode(seed.to_bytes())
+            );
+            print_transfers(&transfers);
+        }
+        Command::Watch {
+            dir,
+            password,
+            interval,
+            check_ledger,
+        } => {
+            let wallet = secure_wallet(&cli.profile, password)?;
+            confirm_network(&config, cli.confirm_network.as_deref())?;
+
+            Watch {
+                dir,
+                profile: &cli.profile,
+                wallet,
+                config,
+                metrics: &metrics,
+                metrics_file: cli.metrics_file,
+                interval: Duration::from_secs(interval),
+                check_ledger,
+            }
+            .run()
+            .await?;
+        }
+        Command::Audit => {
+            let sent =
+                SentRegistry::open(record(&cli.profile, &config, "sent"))?;
+            let entries = AuditLog::verify(cli.profile.join(AUDIT_LOG), &sent)?;
+            println!("{} entries, the hash chain is intact", entries.len());
+        }
+        Command::Config => print!("{}", config.redacted()?),
+        Command::Status { tx_id } => {
+            let gql = GraphQL::new(config.graphql_address);
+
+            match gql.tx_status(&tx_id).await? {
+                TxStatus::Ok {
+                    block_height: Some(height),
+                } => println!("{tx_id}: confirmed at height {height}"),
+                TxStatus::Ok { block_height: None } => {
+                    println!("{tx_id}: confirmed")
+                }
+                TxStatus::NotFound => println!("{tx_id}: not found"),
+                TxStatus::Error(err) => println!("{tx_id}: failed: {err}"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// read the input file into a map of transfers, the ledger of the accounts
+// is checked unless skipped
+fn parse(
+    input: &Input,
+    config: &Config,
+) -> anyhow::Result<(Ledger, TransferMap)> {
+    // days are read from the TAI64 timestamps of the input, not the unix
+    // milliseconds set by `--now`
+    if input.now && config.batch_by_day {
+        anyhow::bail!("--now can't be used with batches split by day");
+    }
+
+    let ts_override = input.now.then(|| {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
             .as_millis() as u64
     });
 
-    let data = json_file(cli.json_path, ts_override)?;
-    WalletPath::set_cache_dir(&cli.profile)?;
-    let wallet_path =
-        WalletPath::from(cli.profile.as_path().join("wallet.dat"));
-    let config_path = cli.profile.as_path().join("gov_config.toml");
+    let options = ParseOptions {
+        timestamp: ts_override,
+        registry: config.registry()?,
+        keys: config.keys()?,
+        derive_keys: config.derive_keys,
+        from: input.from,
+        to: input.to,
+    };
+    let format = input
+        .format
+        .unwrap_or_else(|| Format::from_path(&input.json_path));
+    let bytes = fs::read(&input.json_path)?;
+    let accounts = match format {
+        Format::Json => json_events(bytes)?,
+        Format::Csv => csv_events(bytes)?,
+    };
 
-    let wallet = SecureWallet {
-        pwd: cli.password,
-        path: wallet_path,
+    check_rebalances(&accounts, config.rebalance_tolerance)?;
+
+    let ledger = Ledger::from_events(&accounts);
+    if input.check_ledger {
+        ledger.check()?;
+    } else {
+        for issue in ledger.issues() {
+            tracing::warn!("{issue}");
+        }
+    }
+    let data = transfer_map(accounts, &options)?;
+
+    Ok((ledger, data))
+}
+
+fn print_dry_run(
+    contract: Governance,
+    data: TransferMap,
+) -> anyhow::Result<()> {
+    for DryRun {
+        security,
+        kind,
+        count,
+        contract_id,
+        payload,
+    } in contract.dry_run(data)?
+    {
+        println!(
+            "{security} {kind}: {count} transfer(s), {} bytes to {}",
+            payload.len(),
+            hex::encode(contract_id.as_bytes())
+        );
+        println!("{}", hex::encode(payload));
+    }
+
+    Ok(())
+}
+
+// write the receipts of the run, also when it stopped on a failure
+fn write_receipts(
+    path: Option<PathBuf>,
+    res: &anyhow::Result<Vec<Receipt>>,
+) -> anyhow::Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let receipts = match res {
+        Ok(receipts) => receipts,
+        Err(err) => match err.downcast_ref::<Receipts>() {
+            Some(Receipts(receipts)) => receipts,
+            None => return Ok(()),
+        },
     };
 
-    let contract = Governance::new(wallet, config_path)?;
+    match Format::from_path(&path) {
+        Format::Json => fs::write(&path, serde_json::to_vec_pretty(receipts)?)?,
+        Format::Csv => {
+            let mut writer = csv::Writer::from_path(&path)?;
+            for receipt in receipts {
+                writer.serialize(receipt)?;
+            }
+            writer.flush()?;
+        }
+    }
 
-    contract.send_data(data).await?;
+    println!(
+        "{} receipt(s) written to {}",
+        receipts.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+// governance keeping its journal and sent batches in the profile directory
+fn governance(
+    profile: &Path,
+    wallet: SecureWallet,
+    mut config: Config,
+    metrics: &Metrics,
+    resend: bool,
+) -> Governance {
+    if resend {
+        config.duplicates = DuplicatePolicy::Resend;
+    }
+
+    let journal = record(profile, &config, "journal");
+    let sent = record(profile, &config, "sent");
+
+    let mut contract = Governance::from_config(wallet, config);
+    contract.with_journal(journal);
+    contract.with_sent_registry(sent);
+    // the audit log can't be turned off, it covers every network
+    contract.with_audit_log(profile.join(AUDIT_LOG));
+    contract.with_metrics(metrics.clone());
+    contract
+}
+
+// path of a record kept in the profile directory, e.g. `sent-testnet.json`
+fn record(profile: &Path, config: &Config, name: &str) -> PathBuf {
+    // runs on different networks never share their records
+    match &config.network {
+        Some(network) => profile.join(format!("{name}-{network}.json")),
+        None => profile.join(format!("{name}.json")),
+    }
+}
+
+// the timestamps set by `--now` change at every run, so the sent registry
+// can't recognize an input sent before. Refuse the batches holding transfers
+// that only differ from confirmed ones by their timestamp.
+fn check_now(
+    profile: &Path,
+    config: &Config,
+    batches: &[Batch],
+) -> anyhow::Result<()> {
+    let sent = SentRegistry::open(record(profile, config, "sent"))?;
+    let duplicates: Vec<_> = batches
+        .iter()
+        .filter_map(|batch| {
+            let prev = sent.resembles(batch)?;
+            Some(Duplicate::new(batch, prev).to_string())
+        })
+        .collect();
+
+    if !duplicates.is_empty() {
+        anyhow::bail!(
+            "--now can't be used, transfers were already sent and duplicates \
+             would not be detected, see --allow-now:\n  {}",
+            duplicates.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+// write the metrics of the run, if asked to
+fn write_metrics(path: Option<PathBuf>, metrics: &Metrics) -> io::Result<()> {
+    match path {
+        Some(path) => metrics.write(path),
+        None => Ok(()),
+    }
+}
+
+// answer every HTTP request on the address with the current metrics, until
+// the run ends
+async fn serve_metrics(addr: SocketAddr, metrics: Metrics) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("Serving metrics on http://{addr}/metrics");
+
+    tokio::spawn(async move {
+        loop {
+            // e.g. out of file descriptors, the next connections may succeed
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!("Failed to accept a metrics scrape: {err}");
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+
+            // a client that never sends nor reads only holds its own task,
+            // until the timeout closes the connection
+            let body = metrics.render();
+            tokio::spawn(async move {
+                let reply = async {
+                    let mut request = [0; 1024];
+                    let _ = stream.read(&mut request).await;
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                         Content-Type: text/plain; version=0.0.4\r\n\
+                         Content-Length: {}\r\n\
+                         Connection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    stream.write_all(response.as_bytes()).await
+                };
+
+                if tokio::time::timeout(SCRAPE_TIMEOUT, reply).await.is_err() {
+                    tracing::warn!("Metrics scrape timed out");
+                }
+            });
+        }
+    });
 
     Ok(())
 }
+
+// sending to a production network needs its name, typed or passed with
+// `--confirm-network`
+fn confirm_network(
+    config: &Config,
+    confirmed: Option<&str>,
+) -> anyhow::Result<()> {
+    if !config.production {
+        return Ok(());
+    }
+
+    let name = config.network.as_deref().unwrap_or("production");
+    if confirmed == Some(name) {
+        return Ok(());
+    }
+
+    eprint!("Sending to the production network {name}, type its name: ");
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+
+    if line.trim() != name {
+        anyhow::bail!("Sending to {name} was not confirmed");
+    }
+    Ok(())
+}
+
+// wallet stored in the profile directory
+fn secure_wallet(
+    profile: &Path,
+    password: String,
+) -> anyhow::Result<SecureWallet> {
+    WalletPath::set_cache_dir(profile)?;
+    let wallet_path = WalletPath::from(profile.join("wallet.dat"));
+
+    Ok(SecureWallet {
+        pwd: password,
+        path: wallet_path,
+    })
+}
+
+/// Decode a base58 encoded BLS public key
+fn public_key(text: &str) -> anyhow::Result<BlsPublicKey> {
+    let key: [u8; BlsPublicKey::SIZE] = bs58::decode(text.trim())
+        .into_vec()?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid public key length"))?;
+
+    BlsPublicKey::from_bytes(&key)
+        .map_err(|e| anyhow::anyhow!("Invalid public key: {e:?}"))
+}
+
+fn print_batch(batch: &Batch) {
+    println!(
+        "{} {}: {} transfer(s) to {}, seed {}",
+        batch.security,
+        batch.kind,
+        batch.transfers.len(),
+        hex::encode(batch.contract_id.as_bytes()),
+        hex::encode(batch.seed().to_bytes())
+    );
+    print_transfers(&batch.transfers);
+}
+
+fn print_transfers(transfers: &[Transfer]) {
+    for tx in transfers {
+        let (direction, key) = match (tx.to, tx.from) {
+            (Some(to), _) => ("deposit to", to),
+            (None, Some(from)) => ("withdraw from", from),
+            (None, None) => continue,
+        };
+
+        println!(
+            "  {} {direction} {} at {}",
+            tx.decimal_amount(),
+            bs58::encode(key.to_bytes()).into_string(),
+            tx.timestamp
+        );
+    }
+}
@@ -0,0 +1,223 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use governance_core::prelude::*;
+use tracing::{info, warn};
+
+use crate::args::Input;
+use crate::{governance, parse, record, write_metrics, write_receipts};
+
+/// Directory of the files sent, in the watched one
+const PROCESSED: &str = "processed";
+/// Directory of the files that failed, in the watched one
+const FAILED: &str = "failed";
+
+/// Daemon sending the files dropped in a directory
+pub struct Watch<'a> {
+    pub dir: PathBuf,
+    pub profile: &'a Path,
+    pub wallet: SecureWallet,
+    pub config: Config,
+    pub metrics: &'a Metrics,
+    pub metrics_file: Option<PathBuf>,
+    pub interval: Duration,
+    pub check_ledger: bool,
+}
+
+impl Watch<'_> {
+    /// Process the new files of the directory until interrupted
+    ///
+    /// A file is moved once handled, so a file still in the directory after
+    /// a restart is sent again: its own journal, named after the hash of the
+    /// file, resumes its pending batch and the confirmed ones are skipped
+    pub async fn run(self) -> anyhow::Result<()> {
+        fs::create_dir_all(self.dir.join(PROCESSED))?;
+        fs::create_dir_all(self.dir.join(FAILED))?;
+
+        info!("Watching {}", self.dir.display());
+
+        // size of the files at the previous scan, a file is picked up once
+        // it stopped growing
+        let mut sizes = HashMap::new();
+        // files handled but left in the directory, they are not sent again
+        let mut stuck = HashSet::new();
+
+        loop {
+            let ready = self.ready(&mut sizes).unwrap_or_else(|err| {
+                warn!("Failed to scan {}: {err:#}", self.dir.display());
+                vec![]
+            });
+
+            for path in ready {
+                if stuck.contains(&path) {
+                    continue;
+                }
+
+                let name = path.file_name().expect("listed file").to_owned();
+                let res = self.process(&path).await;
+                match &res {
+                    Ok(receipts) => {
+                        info!("Sent {} batch(es) of {name:?}", receipts.len())
+                    }
+                    Err(err) => warn!("Failed to send {name:?}: {err:#}"),
+                }
+
+                if let Err(err) = self.store(&path, &res) {
+                    warn!("Failed to move {name:?}: {err}, skipping it");
+                    stuck.insert(path);
+                }
+                if let Err(err) =
+                    write_metrics(self.metrics_file.clone(), self.metrics)
+                {
+                    warn!("Failed to write the metrics: {err}");
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => (),
+                _ = tokio::signal::ctrl_c() => {
+                    info!("Stopped watching {}", self.dir.display());
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // move a handled file to `processed` or `failed`, along with its
+    // receipts and error. Only a file left in the directory is an error.
+    fn store(
+        &self,
+        path: &Path,
+        res: &anyhow::Result<Vec<Receipt>>,
+    ) -> io::Result<()> {
+        let dir = self.dir.join(match res {
+            Ok(_) => PROCESSED,
+            Err(_) => FAILED,
+        });
+        let name = free_name(&dir, path);
+
+        if let Err(err) = res {
+            let error = dir.join(with_suffix(&name, ".error"));
+            if let Err(e) = fs::write(&error, format!("{err:#}\n")) {
+                warn!("Failed to write {}: {e}", error.display());
+            }
+        }
+
+        let receipt = dir.join(with_suffix(&name, ".receipt.json"));
+        if let Err(e) = write_receipts(Some(receipt.clone()), res) {
+            warn!("Failed to write {}: {e:#}", receipt.display());
+        }
+
+        fs::rename(path, dir.join(name))
+    }
+
+    // input files whose size didn't change since the previous scan, by name
+    fn ready(
+        &self,
+        sizes: &mut HashMap<PathBuf, u64>,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut ready = vec![];
+        let mut seen = HashMap::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !path.is_file() || !is_input(&path) {
+                continue;
+            }
+
+            let size = fs::metadata(&path)?.len();
+            if sizes.get(&path) == Some(&size) {
+                ready.push(path.clone());
+            }
+            seen.insert(path, size);
+        }
+
+        *sizes = seen;
+        ready.sort();
+        Ok(ready)
+    }
+
+    async fn process(&self, path: &Path) -> anyhow::Result<Vec<Receipt>> {
+        let input = Input {
+            json_path: path.to_path_buf(),
+            format: None,
+            now: false,
+            allow_now: false,
+            check_ledger: self.check_ledger,
+            from: None,
+            to: None,
+            by_day: false,
+        };
+        let (_, data) = parse(&input, &self.config)?;
+
+        // files are sent one after the other, each keeping its own journal
+        let hash = blake3::hash(&fs::read(path)?).to_hex();
+        let journal = format!("journal-{}", &hash[..16]);
+        let journal = record(self.profile, &self.config, &journal);
+
+        let mut contract = governance(
+            self.profile,
+            self.wallet.clone(),
+            self.config.clone(),
+            self.metrics,
+            false,
+        );
+        contract.with_journal(journal);
+        contract.send_data(data).await
+    }
+}
+
+// JSON and CSV exports
+fn is_input(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => {
+            ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("csv")
+        }
+        None => false,
+    }
+}
+
+// name followed by the suffix, e.g. `data.json.error`
+fn with_suffix(name: &OsStr, suffix: &str) -> OsString {
+    let mut name = name.to_owned();
+    name.push(suffix);
+    name
+}
+
+// name of the file once moved to `dir`, numbered when a file of the same
+// name was moved there before, e.g. `data.1.json`
+fn free_name(dir: &Path, path: &Path) -> OsString {
+    let stem = path.file_stem().expect("listed file");
+    let ext = path.extension().expect("input file");
+    let taken = |name: &OsStr| {
+        let names = [
+            name.to_owned(),
+            with_suffix(name, ".receipt.json"),
+            with_suffix(name, ".error"),
+        ];
+        names.iter().any(|name| dir.join(name).exists())
+    };
+
+    (0..)
+        .map(|n| {
+            let mut name = stem.to_owned();
+            match n {
+                0 => name.push("."),
+                n => name.push(format!(".{n}.")),
+            }
+            name.push(ext);
+            name
+        })
+        .find(|name| !taken(name))
+        .expect("unbounded names")
+}
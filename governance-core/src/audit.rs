@@ -0,0 +1,251 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use canonical::EncodeToVec;
+use chrono::Utc;
+use dusk_bls12_381_sign::{
+    PublicKey as BlsPublicKey, SecretKey as BlsSecretKey, Signature,
+};
+use dusk_bytes::Serializable;
+use serde::{Deserialize, Serialize};
+
+use crate::journal::key;
+use crate::models::Batch;
+use crate::sent::SentRegistry;
+use crate::transport::SignedPayload;
+
+/// Hash preceding the first entry of a log
+const GENESIS: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Record of a payload broadcast to the blockchain, one JSON line of the
+/// audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// RFC 3339 time the payload was broadcast at
+    pub time: String,
+    pub network: Option<String>,
+    pub security: String,
+    /// Hex encoded id of the contract the payload was sent to
+    pub contract: String,
+    pub op: u8,
+    /// Hex encoded seed of the payload
+    pub seed: String,
+    /// Hex encoded BLAKE3 hash of the canonical encoding of the signed
+    /// payload
+    pub payload_hash: String,
+    /// bs58 encoded public key of the provisioner, if known
+    pub signer: Option<String>,
+    pub tx_hash: String,
+    /// Hash of the previous entry
+    pub prev: String,
+    /// Hash of this entry, chained to the previous one
+    pub hash: String,
+    /// bs58 encoded signature of `hash` by the `signer` key, missing when
+    /// the payload was signed on another host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl AuditEntry {
+    // hash of the entry fields and of the previous entry
+    fn digest(&self) -> String {
+        let fields = (
+            &self.time,
+            &self.network,
+            &self.security,
+            &self.contract,
+            self.op,
+            &self.seed,
+            &self.payload_hash,
+            &self.signer,
+            &self.tx_hash,
+            &self.prev,
+        );
+        let json = serde_json::to_vec(&fields).expect("serializable fields");
+
+        blake3::hash(&json).to_hex().to_string()
+    }
+
+    // whether the signature, if any, is the one of the hash by the signer
+    fn signed(&self) -> bool {
+        let Some(signature) = &self.signature else {
+            return true;
+        };
+        let Some(signer) = &self.signer else {
+            return false;
+        };
+
+        let decode = |text: &str| bs58::decode(text).into_vec().ok();
+        let pk = decode(signer)
+            .and_then(|bytes| bytes.try_into().ok())
+            .and_then(|bytes| BlsPublicKey::from_bytes(&bytes).ok());
+        let signature = decode(signature)
+            .and_then(|bytes| bytes.try_into().ok())
+            .and_then(|bytes| Signature::from_bytes(&bytes).ok());
+
+        match (pk, signature) {
+            (Some(pk), Some(signature)) => {
+                pk.verify(&signature, self.hash.as_bytes()).is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Append-only log of the payloads broadcast, every entry holding the hash
+/// of the previous one so an edited or removed line breaks the chain
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    last: String,
+    key: Option<BlsSecretKey>,
+}
+
+impl AuditLog {
+    /// Open the log stored at `path`, checking the entries already written
+    /// against the batches confirmed in `sent`
+    pub fn open<P: Into<PathBuf>>(
+        path: P,
+        sent: &SentRegistry,
+    ) -> Result<Self, AuditError> {
+        let path = path.into();
+        let last = Self::verify(&path, sent)?
+            .pop()
+            .map_or_else(|| GENESIS.to_string(), |entry| entry.hash);
+
+        Ok(Self {
+            path,
+            last,
+            key: None,
+        })
+    }
+
+    /// Entries of the log at `path`, failing at the first one breaking the
+    /// chain or with a wrong signature. The log must hold the last entry
+    /// recorded by `sent`, and can't be empty once batches were confirmed.
+    pub fn verify<P: AsRef<Path>>(
+        path: P,
+        sent: &SentRegistry,
+    ) -> Result<Vec<AuditEntry>, AuditError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut prev = GENESIS.to_string();
+        let mut entries = vec![];
+        for (index, line) in content.lines().enumerate() {
+            let entry: AuditEntry = serde_json::from_str(line)
+                .map_err(|_| AuditError::Broken(index + 1))?;
+            if entry.prev != prev || entry.digest() != entry.hash {
+                return Err(AuditError::Broken(index + 1));
+            }
+            if !entry.signed() {
+                return Err(AuditError::Signature(index + 1));
+            }
+
+            prev = entry.hash.clone();
+            entries.push(entry);
+        }
+
+        match sent.audit_head() {
+            Some(head) if !entries.iter().any(|entry| entry.hash == head) => {
+                Err(AuditError::Truncated(head.to_string()))
+            }
+            _ if entries.is_empty() && !sent.is_empty() => {
+                Err(AuditError::Missing)
+            }
+            _ => Ok(entries),
+        }
+    }
+
+    /// Sign the hash of every entry recorded with the provisioner key
+    pub fn with_key(&mut self, sk: BlsSecretKey) {
+        self.key = Some(sk);
+    }
+
+    /// Hash of the last entry of the log
+    pub fn head(&self) -> &str {
+        &self.last
+    }
+
+    /// Append the broadcast of a batch in the transaction `tx_hash`
+    pub fn record(
+        &mut self,
+        network: Option<&str>,
+        batch: &Batch,
+        payload: &SignedPayload,
+        signer: Option<&str>,
+        tx_hash: &str,
+    ) -> Result<(), AuditError> {
+        let (contract, op, seed) = key(batch);
+        let mut entry = AuditEntry {
+            time: Utc::now().to_rfc3339(),
+            network: network.map(str::to_string),
+            security: batch.security.to_string(),
+            contract,
+            op,
+            seed,
+            payload_hash: blake3::hash(&payload.encode_to_vec())
+                .to_hex()
+                .to_string(),
+            signer: signer.map(str::to_string),
+            tx_hash: tx_hash.to_string(),
+            prev: self.last.clone(),
+            hash: String::new(),
+            signature: None,
+        };
+        entry.hash = entry.digest();
+        entry.signature = self.key.map(|sk| {
+            let signature =
+                sk.sign(&BlsPublicKey::from(&sk), entry.hash.as_bytes());
+            bs58::encode(signature.to_bytes()).into_string()
+        });
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+
+        self.last = entry.hash;
+        Ok(())
+    }
+}
+
+/// Errors of the audit log
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    /// The log could not be read or written
+    #[error("Failed to access the audit log: {0}")]
+    Io(#[from] io::Error),
+    /// An entry could not be written
+    #[error("Failed to write the audit log: {0}")]
+    Json(#[from] serde_json::Error),
+    /// A line was edited, removed or inserted
+    #[error("The audit log was altered at line {0}")]
+    Broken(usize),
+    /// An entry recorded outside the log is missing, the log was truncated
+    /// or removed
+    #[error("The audit log lost the entry {0}")]
+    Truncated(String),
+    /// Batches were confirmed but the log is empty, it was removed
+    #[error("The audit log is missing, batches were confirmed")]
+    Missing,
+    /// The signature of an entry is not the one of its signer
+    #[error("The audit log has a wrong signature at line {0}")]
+    Signature(usize),
+}
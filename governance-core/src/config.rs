@@ -4,20 +4,308 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
 use dusk_wallet::{SecureWalletFile, WalletPath};
 use serde::{Deserialize, Serialize};
 use toml_base_config::BaseConfig;
 
-#[derive(Default, Deserialize, Serialize)]
+use crate::keys::KeyRegistry;
+use crate::models::{BatchLimits, Decimal};
+use crate::registry::Registry;
+use crate::retry::RetryPolicy;
+use crate::sent::DuplicatePolicy;
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Config {
     pub rusk_address: String,
     pub prover_address: String,
     pub graphql_address: String,
+    /// Name of the network of the addresses
+    pub network: Option<String>,
+    /// Whether the addresses are of a production network, sending to it
+    /// needs an extra confirmation
+    #[serde(default)]
+    pub production: bool,
     pub gas_limit: u64,
     pub gas_price: Option<u64>,
+    /// Seconds to wait for a transaction to be confirmed
+    #[serde(default = "default_confirmation_timeout")]
+    pub confirmation_timeout: u64,
+    /// Milliseconds between two queries of the transaction status
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+    /// Maximum number of transfers sent in a single transaction
+    pub max_batch_transfers: Option<usize>,
+    /// Maximum size in bytes of the signed payload sent in a single
+    /// transaction
+    pub max_batch_bytes: Option<usize>,
+    /// Send the transfers of every calendar day (UTC) in separate batches
+    #[serde(default)]
+    pub batch_by_day: bool,
+    /// Largest difference accepted between the cash leg of a rebalance and
+    /// the value of its securities, relative to that value
+    #[serde(default = "default_rebalance_tolerance")]
+    pub rebalance_tolerance: Decimal,
+    /// What to do with batches confirmed by an earlier run
+    #[serde(default)]
+    pub duplicates: DuplicatePolicy,
+    /// Base58 encoded BLS public key of the provisioner, offline files are
+    /// submitted only when signed by it
+    pub provisioner_key: Option<String>,
+    /// File holding the `[securities]` registry, replaces `securities`
+    pub registry_path: Option<PathBuf>,
+    /// File holding the `[accounts]` keys, replaces `accounts`
+    pub keys_path: Option<PathBuf>,
+    /// Derive the keys of unmapped accounts from their name
+    #[serde(default)]
+    pub derive_keys: bool,
+    /// Retries of the transient errors of the node
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Contracts of the listed securities, by ticker
+    #[serde(default)]
+    pub securities: Registry,
+    /// Public keys of the accounts, by account name or external id
+    #[serde(default)]
+    pub accounts: KeyRegistry,
+    /// Named networks, replacing the addresses once selected
+    #[serde(default)]
+    pub networks: BTreeMap<String, Network>,
+}
+
+/// Addresses of a network
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Network {
+    pub rusk_address: String,
+    pub prover_address: String,
+    pub graphql_address: String,
+    /// Sending to a production network needs an extra confirmation
+    #[serde(default)]
+    pub production: bool,
+}
+
+impl Config {
+    /// Registry of the listed securities
+    pub fn registry(&self) -> io::Result<Registry> {
+        match &self.registry_path {
+            Some(path) => Registry::load_path(path),
+            None => Ok(self.securities.clone()),
+        }
+    }
+
+    /// Public keys of the accounts
+    pub fn keys(&self) -> io::Result<KeyRegistry> {
+        match &self.keys_path {
+            Some(path) => KeyRegistry::load_path(path),
+            None => Ok(self.accounts.clone()),
+        }
+    }
+
+    /// Use the addresses of a named network
+    pub fn select_network(&mut self, name: &str) -> Result<(), NetworkError> {
+        let network = self.networks.get(name).cloned().ok_or_else(|| {
+            let known = self.networks.keys().cloned().collect();
+            NetworkError::Unknown(name.to_string(), known)
+        })?;
+
+        self.rusk_address = network.rusk_address;
+        self.prover_address = network.prover_address;
+        self.graphql_address = network.graphql_address;
+        self.production = network.production;
+        self.network = Some(name.to_string());
+
+        Ok(())
+    }
+
+    /// Replace a field with a value given as text, an empty value unsets an
+    /// optional field
+    pub fn set(
+        &mut self,
+        field: &str,
+        value: &str,
+    ) -> Result<(), OverrideError> {
+        if !OVERRIDABLE.contains(&field) {
+            return Err(OverrideError::UnknownField(field.to_string()));
+        }
+
+        let invalid =
+            |reason: String| OverrideError::Invalid(field.to_string(), reason);
+
+        let mut table = match toml::Value::try_from(&*self) {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => unreachable!("the config is a table"),
+            Err(e) => return Err(invalid(e.to_string())),
+        };
+
+        if value.is_empty() {
+            table.remove(field);
+        } else {
+            table.insert(field.to_string(), override_value(value));
+        }
+
+        *self = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Apply the `GOV_*` variables naming a field, e.g. `GOV_GAS_LIMIT`
+    pub fn apply_env<I>(&mut self, vars: I) -> Result<(), OverrideError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        for (name, value) in vars {
+            let field = match name.strip_prefix(ENV_PREFIX) {
+                Some(field) => field.to_lowercase(),
+                None => continue,
+            };
+
+            if OVERRIDABLE.contains(&field.as_str()) {
+                self.set(&field, &value)?;
+            } else if !ENV_ARGS.contains(&name.as_str()) {
+                tracing::warn!("Ignoring {name}, not a config field");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The config as TOML, with the credentials of the addresses hidden
+    pub fn redacted(&self) -> Result<String, toml::ser::Error> {
+        let mut config = toml::Value::try_from(self)?;
+        redact(&mut config);
+        toml::to_string_pretty(&config)
+    }
+
+    /// Limits used to split the transfers of a security into batches
+    pub fn batch_limits(&self) -> BatchLimits {
+        BatchLimits {
+            max_transfers: self.max_batch_transfers,
+            max_bytes: self.max_batch_bytes,
+            by_day: self.batch_by_day,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            rusk_address: String::default(),
+            prover_address: String::default(),
+            graphql_address: String::default(),
+            network: None,
+            production: false,
+            gas_limit: 0,
+            gas_price: None,
+            confirmation_timeout: default_confirmation_timeout(),
+            poll_interval: default_poll_interval(),
+            max_batch_transfers: None,
+            max_batch_bytes: None,
+            batch_by_day: false,
+            rebalance_tolerance: default_rebalance_tolerance(),
+            duplicates: DuplicatePolicy::default(),
+            provisioner_key: None,
+            registry_path: None,
+            keys_path: None,
+            derive_keys: false,
+            retry: RetryPolicy::default(),
+            securities: Registry::default(),
+            accounts: KeyRegistry::default(),
+            networks: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_confirmation_timeout() -> u64 {
+    100
+}
+
+fn default_poll_interval() -> u64 {
+    1000
+}
+
+fn default_rebalance_tolerance() -> Decimal {
+    Decimal::new(1, 3)
+}
+
+/// Prefix of the environment variables overriding the config
+pub const ENV_PREFIX: &str = "GOV_";
+
+/// Variables read by the command line rather than the config, `GOV_NETWORK`
+/// selects a network along with its `production` flag
+const ENV_ARGS: &[&str] = &["GOV_NETWORK"];
+
+/// Fields that can be replaced from the environment or the command line
+const OVERRIDABLE: &[&str] = &[
+    "rusk_address",
+    "prover_address",
+    "graphql_address",
+    "gas_limit",
+    "gas_price",
+    "confirmation_timeout",
+    "poll_interval",
+    "max_batch_transfers",
+    "max_batch_bytes",
+    "batch_by_day",
+    "rebalance_tolerance",
+    "duplicates",
+    "registry_path",
+    "keys_path",
+    "derive_keys",
+];
+
+/// Read the text as a TOML value, anything else is a string
+fn override_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::value::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_string()))
+}
+
+/// Hide the user and password of every URL in the value
+fn redact(value: &mut toml::Value) {
+    match value {
+        toml::Value::String(text) => {
+            if let Some(start) = text.find("://").map(|i| i + 3) {
+                let host =
+                    text[start..].find('/').map_or(text.len(), |i| i + start);
+                if let Some(at) = text[start..host].rfind('@') {
+                    text.replace_range(start..start + at, "***");
+                }
+            }
+        }
+        toml::Value::Table(table) => {
+            table.iter_mut().for_each(|(_, value)| redact(value))
+        }
+        toml::Value::Array(array) => array.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Errors overriding a field of the config
+#[derive(Debug, thiserror::Error)]
+pub enum OverrideError {
+    /// The field is not in the config or can't be overridden
+    #[error("Unknown config field {0}")]
+    UnknownField(String),
+    /// The value doesn't fit the field
+    #[error("Invalid value for {0}: {1}")]
+    Invalid(String, String),
+}
+
+/// Errors selecting a network
+#[derive(Debug, thiserror::Error)]
+pub enum NetworkError {
+    /// The network is not in the config
+    #[error("Unknown network {0}, the config lists {1:?}")]
+    Unknown(String, Vec<String>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SecureWallet {
     pub path: WalletPath,
     pub pwd: String,
@@ -0,0 +1,109 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::models::events::{to_decimal_str, to_tai64_timestamp};
+use crate::prelude::*;
+
+/// A single row of the CSV export, each row holds one change
+#[derive(Deserialize)]
+struct Row {
+    account: String,
+    #[serde(deserialize_with = "to_tai64_timestamp")]
+    occurrence: u64,
+    cause: Cause,
+    #[serde(rename = "type")]
+    change_type: ChangeType,
+    security: SecurityDefinition,
+    #[serde(deserialize_with = "to_decimal_str")]
+    size: Decimal,
+    #[serde(deserialize_with = "to_decimal_str")]
+    price: Decimal,
+}
+
+/// Parse a csv file, convert them to a map of Transfers
+pub fn csv_file<T: AsRef<Path>>(
+    path: T,
+    options: &ParseOptions,
+) -> Result<TransferMap, ParseError> {
+    let mut data = String::new();
+    let f = File::open(path.as_ref())?;
+
+    let mut reader = BufReader::new(f);
+    reader.read_to_string(&mut data)?;
+
+    csv_bytes(data.as_bytes(), options)
+}
+
+/// Parse raw csv bytes convert them to a map of Transfers
+///
+/// The header must contain the columns `account`, `occurrence`, `cause`,
+/// `type`, `security`, `size` and `price`. Consecutive rows of the same
+/// account sharing `occurrence` and `cause` are grouped into one event.
+pub fn csv_bytes<T: AsRef<[u8]>>(
+    bytes: T,
+    options: &ParseOptions,
+) -> Result<TransferMap, ParseError> {
+    transfer_map(csv_events(bytes)?, options)
+}
+
+/// Parse raw csv bytes into the events of every account, grouped as in
+/// [`csv_bytes`]
+pub fn csv_events<T: AsRef<[u8]>>(
+    bytes: T,
+) -> Result<Vec<(String, Events)>, ParseError> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .trim(::csv::Trim::All)
+        .from_reader(bytes.as_ref());
+
+    let mut accounts: BTreeMap<String, Events> = BTreeMap::new();
+
+    for row in reader.deserialize() {
+        let Row {
+            account,
+            occurrence,
+            cause,
+            change_type,
+            security,
+            size,
+            price,
+        } = row?;
+
+        let change = Change {
+            account_external_id: account.clone(),
+            change_type,
+            size,
+            security,
+            price,
+        };
+
+        let events = &mut accounts
+            .entry(account)
+            .or_insert_with(|| Events { events: vec![] })
+            .events;
+
+        match events.last_mut() {
+            Some(event)
+                if event.occurrence == occurrence && event.cause == cause =>
+            {
+                event.changes.push(change)
+            }
+            _ => events.push(Event {
+                cause,
+                changes: vec![change],
+                occurrence,
+            }),
+        }
+    }
+
+    Ok(accounts.into_iter().collect())
+}
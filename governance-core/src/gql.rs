@@ -4,27 +4,30 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
-use std::{thread::sleep, time::Duration};
-
-use dusk_wallet::Error;
+use async_trait::async_trait;
 use gql_client::{Client, GraphQLErrorMessage};
 use serde::Deserialize;
 use serde_json::Value;
 
+use crate::metrics::Metrics;
+use crate::transport::StatusProvider;
+
 /// GraphQL is a helper struct that aggregates all queries done
 /// to the Dusk GraphQL database.
 /// This helps avoid having helper structs and boilerplate code
 /// mixed with the wallet logic.
 #[derive(Clone)]
-pub struct GraphQL<S> {
+pub struct GraphQL {
     url: String,
-    status: S,
+    metrics: Metrics,
 }
 
 // helper structs to deserialize response
 #[derive(Deserialize)]
 struct Tx {
     pub txerror: String,
+    #[serde(default)]
+    pub blockheight: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -35,7 +38,10 @@ struct Transactions {
 /// Transaction status
 #[derive(Debug)]
 pub enum TxStatus {
-    Ok,
+    /// Included in a block, at the given height if the node reports it
+    Ok {
+        block_height: Option<u64>,
+    },
     NotFound,
     Error(String),
 }
@@ -46,63 +52,45 @@ fn is_database_error(json: &[GraphQLErrorMessage]) -> bool {
     format!("{:?}", json[0]).contains("database: transaction not found")
 }
 
-impl<T> GraphQL<T>
-where
-    T: Fn(&str),
-{
+impl GraphQL {
     /// Create a new GraphQL wallet client
-    pub fn new<S>(url: S, status: T) -> Self
+    pub fn new<S>(url: S) -> Self
     where
         S: Into<String>,
     {
         Self {
             url: url.into(),
-            status,
+            metrics: Metrics::default(),
         }
     }
 
-    /// Wait for a transaction to be confirmed (included in a block)
-    pub async fn wait_for(&self, tx_id: &str) -> anyhow::Result<()> {
-        const TIMEOUT_SECS: i32 = 100;
-        let mut i = 1;
-        while i <= TIMEOUT_SECS {
-            let status = self.tx_status(tx_id).await?;
-
-            match status {
-                TxStatus::Ok => break,
-                TxStatus::Error(err) => return Err(Error::Transaction(err))?,
-                TxStatus::NotFound => {
-                    (self.status)(
-                        format!(
-                            "Waiting for confirmation... ({}/{})",
-                            i, TIMEOUT_SECS
-                        )
-                        .as_str(),
-                    );
-                    sleep(Duration::from_millis(1000));
-                    i += 1;
-                }
-            }
-        }
-        Ok(())
+    /// Count the status queries in the metrics
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     /// Obtain transaction status
-    async fn tx_status(&self, tx_id: &str) -> Result<TxStatus, GraphQLError> {
+    pub async fn tx_status(
+        &self,
+        tx_id: &str,
+    ) -> Result<TxStatus, GraphQLError> {
         // graphql connection
         let client = Client::new(&self.url);
 
-        let query =
-            "{transactions(txid:\"####\"){ txerror }}".replace("####", tx_id);
+        let query = "{transactions(txid:\"####\"){ txerror blockheight }}"
+            .replace("####", tx_id);
 
         let response = client.query::<Transactions>(&query).await;
 
-        match response {
+        let status = match response {
             Ok(Some(txs)) if txs.transactions.is_empty() => {
                 Ok(TxStatus::NotFound)
             }
             Ok(Some(txs)) if txs.transactions[0].txerror.is_empty() => {
-                Ok(TxStatus::Ok)
+                Ok(TxStatus::Ok {
+                    block_height: txs.transactions[0].blockheight,
+                })
             }
             Ok(Some(txs)) => {
                 let tx = &txs.transactions[0];
@@ -126,7 +114,21 @@ where
                 }
                 _ => Err(GraphQLError::Generic(err)),
             },
-        }
+        };
+
+        self.metrics.status_query(match &status {
+            Ok(TxStatus::Ok { .. }) => "ok",
+            Ok(TxStatus::NotFound) => "not_found",
+            Ok(TxStatus::Error(_)) | Err(_) => "error",
+        });
+        status
+    }
+}
+
+#[async_trait(?Send)]
+impl StatusProvider for GraphQL {
+    async fn tx_status(&self, tx_id: &str) -> Result<TxStatus, GraphQLError> {
+        GraphQL::tx_status(self, tx_id).await
     }
 }
 
@@ -139,6 +141,12 @@ pub enum GraphQLError {
     /// Failed to fetch transaction status
     #[error("Failed to obtain transaction status")]
     TxStatus,
+    /// The transaction was included in a block with an error
+    #[error("Transaction error: {0}")]
+    Transaction(String),
+    /// The transaction was not confirmed in time
+    #[error("Transaction {0} not confirmed after {1}s")]
+    Timeout(String, u64),
 }
 
 impl From<gql_client::GraphQLError> for GraphQLError {
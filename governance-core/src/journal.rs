@@ -0,0 +1,138 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use dusk_bytes::Serializable;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Batch;
+
+/// Confirmation state of a submitted payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxState {
+    Submitted,
+    Confirmed,
+}
+
+/// Record of a payload sent to the blockchain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Hex encoded id of the contract the payload was sent to
+    pub contract: String,
+    pub op: u8,
+    /// Hex encoded seed of the payload
+    pub seed: String,
+    /// See [`Batch::repeat`]
+    #[serde(default)]
+    pub repeat: usize,
+    pub tx_hash: String,
+    pub state: TxState,
+}
+
+impl Entry {
+    fn matches(&self, batch: &Batch) -> bool {
+        let (contract, op, seed) = key(batch);
+        self.contract == contract
+            && self.op == op
+            && self.seed == seed
+            && self.repeat == batch.repeat
+    }
+}
+
+/// Journal of the payloads sent during a run, persisted after every change
+/// so an interrupted run can be resumed without sending them again
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    entries: Vec<Entry>,
+}
+
+impl Journal {
+    /// Open the journal stored at `path`, a missing file is an empty journal
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+
+        let mut journal = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Journal::default(),
+            Err(e) => return Err(e),
+        };
+        journal.path = Some(path);
+
+        Ok(journal)
+    }
+
+    /// Entry recorded for the batch, if it was already submitted
+    pub fn get(&self, batch: &Batch) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.matches(batch))
+    }
+
+    /// Record the batch as submitted in the transaction `tx_hash`
+    pub fn submitted(
+        &mut self,
+        batch: &Batch,
+        tx_hash: &str,
+    ) -> io::Result<()> {
+        self.entries.retain(|entry| !entry.matches(batch));
+
+        let (contract, op, seed) = key(batch);
+        self.entries.push(Entry {
+            contract,
+            op,
+            seed,
+            repeat: batch.repeat,
+            tx_hash: tx_hash.to_string(),
+            state: TxState::Submitted,
+        });
+
+        self.save()
+    }
+
+    /// Record the transaction of the batch as confirmed
+    pub fn confirmed(&mut self, batch: &Batch) -> io::Result<()> {
+        self.entries
+            .iter_mut()
+            .filter(|entry| entry.matches(batch))
+            .for_each(|entry| entry.state = TxState::Confirmed);
+
+        self.save()
+    }
+
+    /// Remove the journal once the whole run is confirmed
+    pub fn finish(self) -> io::Result<()> {
+        match self.path {
+            Some(path) if path.exists() => fs::remove_file(path),
+            _ => Ok(()),
+        }
+    }
+
+    // write to a temporary file first, so a crash never leaves the journal
+    // half written
+    fn save(&self) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+            fs::rename(tmp, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+// contract id, op code and seed identify a payload, along with the repeat
+// of identical chunks
+pub(crate) fn key(batch: &Batch) -> (String, u8, String) {
+    (
+        hex::encode(batch.contract_id.as_bytes()),
+        batch.kind.op(),
+        hex::encode(batch.seed().to_bytes()),
+    )
+}
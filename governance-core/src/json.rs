@@ -4,102 +4,326 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
-use std::io::{
-    self, BufReader, Error as IoError, ErrorKind as IoErrorKind, Read,
-};
+use std::io::{self, BufReader, Read};
 use std::path::Path;
 
+use chrono::{DateTime, Utc};
 use dusk_pki::{PublicKey, SecretKey};
 use rand::{rngs::StdRng, SeedableRng};
+use serde::Deserialize;
 use serde_json::Value;
+use tai64::Tai64;
 
 use crate::prelude::*;
 
+/// Options of the conversion from events to transfers
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Timestamp of every transfer, instead of the occurrence of its event
+    pub timestamp: Option<u64>,
+    /// Securities accepted in the input
+    pub registry: Registry,
+    /// Public keys of the accounts
+    pub keys: KeyRegistry,
+    /// Derive the key of accounts missing from `keys` from their name,
+    /// instead of rejecting them. Anyone knowing the name can recompute the
+    /// secret key, use it only for testing.
+    pub derive_keys: bool,
+    /// Skip the events occurring before this time
+    pub from: Option<DateTime<Utc>>,
+    /// Skip the events occurring at or after this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl ParseOptions {
+    // whether the occurrence, a TAI64 label, is within `from` and `to`
+    fn in_window(&self, occurrence: u64) -> bool {
+        let label = |time: &DateTime<Utc>| Tai64::from_unix(time.timestamp()).0;
+
+        !matches!(&self.from, Some(from) if occurrence < label(from))
+            && !matches!(&self.to, Some(to) if occurrence >= label(to))
+    }
+
+    // key of the account, by name first and then by external id
+    fn account_key(
+        &self,
+        account: &str,
+        external_id: &str,
+    ) -> Result<PublicKey, ChangeError> {
+        self.keys
+            .get(account)
+            .or_else(|| self.keys.get(external_id))
+            .or_else(|| self.derive_keys.then(|| public_key(account)))
+            .ok_or_else(|| ChangeError::UnmappedAccount(account.to_string()))
+    }
+}
+
 /// Parse a json file, convert them to a map of Transfers
 pub fn json_file<T: AsRef<Path>>(
     path: T,
-    timestamp: Option<u64>,
-) -> io::Result<TransferMap> {
+    options: &ParseOptions,
+) -> Result<TransferMap, ParseError> {
     let mut data = String::new();
     let f = File::open(path.as_ref())?;
 
     let mut reader = BufReader::new(f);
     reader.read_to_string(&mut data)?;
 
-    json_bytes(data.as_bytes(), timestamp)
+    json_bytes(data.as_bytes(), options)
 }
 
 /// Parse raw json bytes convert them to a map of Transfers
 pub fn json_bytes<T: AsRef<[u8]>>(
     bytes: T,
-    timestamp: Option<u64>,
-) -> io::Result<TransferMap> {
+    options: &ParseOptions,
+) -> Result<TransferMap, ParseError> {
+    transfer_map(json_events(bytes)?, options)
+}
+
+/// Parse raw json bytes into the events of every account
+pub fn json_events<T: AsRef<[u8]>>(
+    bytes: T,
+) -> Result<Vec<(String, Events)>, ParseError> {
     let json: Value = serde_json::from_slice(bytes.as_ref())?;
 
-    if let Value::Object(obj) = json {
-        // Transfers holds all our transfers
-        let mut map = TransferMap::default();
-        // the account name and all events are the first key value pairs
-        for (account_name, events) in obj {
-            let events: Events = serde_json::from_value(events)?;
-            let events = events.events;
-            let from = public_key(account_name);
+    let obj = match json {
+        Value::Object(obj) => obj,
+        _ => return Err(ParseError::NotAnObject),
+    };
 
-            for Event {
+    let mut accounts = Vec::with_capacity(obj.len());
+    // the account name and all events are the first key value pairs
+    for (account_name, events) in obj {
+        let events = account_events(&account_name, events)?;
+        accounts.push((account_name, events));
+    }
+
+    Ok(accounts)
+}
+
+// deserialize the events one by one, so errors can point to the event
+fn account_events(account: &str, events: Value) -> Result<Events, ParseError> {
+    #[derive(Deserialize)]
+    struct RawEvents {
+        events: Vec<Value>,
+    }
+
+    let RawEvents { events } =
+        serde_json::from_value(events).map_err(|source| {
+            ParseError::Account {
+                account: account.to_string(),
+                source,
+            }
+        })?;
+
+    let events = events
+        .into_iter()
+        .enumerate()
+        .map(|(event, value)| {
+            serde_json::from_value(value).map_err(|source| ParseError::Event {
+                account: account.to_string(),
+                event,
+                source,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Events { events })
+}
+
+/// Convert the events of every account to a map of Transfers
+///
+/// Every change is checked, the error lists all the invalid ones
+pub fn transfer_map<I>(
+    accounts: I,
+    options: &ParseOptions,
+) -> Result<TransferMap, ParseError>
+where
+    I: IntoIterator<Item = (String, Events)>,
+{
+    // Transfers holds all our transfers
+    let mut map = TransferMap::default();
+    let mut invalid = vec![];
+
+    for (account_name, events) in accounts {
+        for (event_idx, event) in events.events.into_iter().enumerate() {
+            let Event {
                 occurrence,
                 changes,
                 cause,
                 ..
-            } in events
-            {
-                for Change {
-                    mut security,
-                    size,
-                    change_type,
-                    ..
-                } in changes
-                {
-                    match change_type {
-                        ChangeType::Reservation => continue,
-                        ChangeType::Cash => security = SecurityDefinition::Cash,
-                        ChangeType::Security => {
-                            assert!(security != SecurityDefinition::None)
-                        }
-                    }
-
-                    let to = public_key(security.to_string());
-
-                    let ts = timestamp.unwrap_or(occurrence);
-                    let mut tx = Transfer::new(size, ts);
-                    match cause {
-                        Cause::Rebalance => {
-                            if size < 0.0 {
-                                tx.amount(-size);
-                                map.insert_tx(security, tx.withdraw(from));
-                            } else {
-                                map.insert_tx(security, tx.deposit(to));
-                            }
-                        }
-                        Cause::Deposit => {
-                            map.insert_tx(security, tx.deposit(to))
-                        }
-                        Cause::Withdrawal => {
-                            map.insert_tx(security, tx.withdraw(from))
-                        }
-                        Cause::Fee => {
-                            map.insert_fee(security, tx.withdraw(from))
-                        }
-                    }
+            } = event;
+
+            if !options.in_window(occurrence) {
+                continue;
+            }
+
+            for (change_idx, change) in changes.into_iter().enumerate() {
+                let ts = options.timestamp.unwrap_or(occurrence);
+                let res = insert_change(
+                    &mut map,
+                    change,
+                    &cause,
+                    &account_name,
+                    ts,
+                    options,
+                );
+
+                if let Err(reason) = res {
+                    invalid.push(InvalidChange {
+                        account: account_name.clone(),
+                        event: event_idx,
+                        change: change_idx,
+                        reason,
+                    });
                 }
             }
         }
-        return Ok(map);
     }
 
-    Err(IoError::from(IoErrorKind::InvalidData))
+    if invalid.is_empty() {
+        Ok(map)
+    } else {
+        Err(ParseError::Changes(invalid))
+    }
+}
+
+// convert a single change to a transfer and add it to the map
+fn insert_change(
+    map: &mut TransferMap,
+    change: Change,
+    cause: &Cause,
+    account: &str,
+    ts: u64,
+    options: &ParseOptions,
+) -> Result<(), ChangeError> {
+    let Change {
+        account_external_id,
+        mut security,
+        size,
+        change_type,
+        ..
+    } = change;
+
+    match change_type {
+        // funds held pending settlement are tracked by the ledger, the
+        // contract has no operation for them
+        ChangeType::Reservation => return Ok(()),
+        ChangeType::Cash => security = SecurityDefinition::Cash,
+        ChangeType::Security if security == SecurityDefinition::None => {
+            return Err(ChangeError::MissingSecurity)
+        }
+        ChangeType::Security => (),
+    }
+
+    if !options.registry.contains(&security) {
+        return Err(ChangeError::UnknownSecurity(security.to_string()));
+    }
+
+    let key = options.account_key(account, &account_external_id)?;
+
+    match cause {
+        Cause::Rebalance if size.is_sign_negative() => {
+            let tx = Transfer::new(-size, ts)?.withdraw(key);
+            map.insert_tx(security, tx);
+        }
+        Cause::Rebalance | Cause::Deposit => {
+            let tx = Transfer::new(size, ts)?.deposit(key);
+            map.insert_tx(security, tx);
+        }
+        // withdrawals and fees are exported as outflows, the direction is
+        // given by the cause
+        Cause::Withdrawal => {
+            let tx = Transfer::new(size.abs(), ts)?.withdraw(key);
+            map.insert_tx(security, tx);
+        }
+        Cause::Fee => {
+            let tx = Transfer::new(size.abs(), ts)?.withdraw(key);
+            map.insert_fee(security, tx);
+        }
+    }
+
+    Ok(())
+}
+
+/// Errors generated while parsing the input events
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    /// The input could not be read
+    #[error("Failed to read the input: {0}")]
+    Io(#[from] io::Error),
+    /// The input is not valid json
+    #[error("Malformed JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The input is not valid csv or a row does not match the columns
+    #[error("Malformed CSV: {0}")]
+    Csv(#[from] ::csv::Error),
+    /// The json root is not a map of account names to events
+    #[error("The JSON root must be an object of accounts")]
+    NotAnObject,
+    /// The events of an account are not a list of events
+    #[error("{account}: malformed events: {source}")]
+    Account {
+        account: String,
+        source: serde_json::Error,
+    },
+    /// A single event cannot be deserialized
+    #[error("{account}: events[{event}]: {source}")]
+    Event {
+        account: String,
+        event: usize,
+        source: serde_json::Error,
+    },
+    /// Some changes cannot be converted to transfers
+    #[error("{}", InvalidChanges(.0))]
+    Changes(Vec<InvalidChange>),
+}
+
+/// A change that cannot be converted to a transfer
+#[derive(Debug, thiserror::Error)]
+#[error("{account}: events[{event}].changes[{change}]: {reason}")]
+pub struct InvalidChange {
+    pub account: String,
+    /// Index of the event in the events of the account
+    pub event: usize,
+    /// Index of the change in the changes of the event
+    pub change: usize,
+    pub reason: ChangeError,
+}
+
+/// Reason a change cannot be converted to a transfer
+#[derive(Debug, thiserror::Error)]
+pub enum ChangeError {
+    /// A security change must name its security
+    #[error("security change without a security definition")]
+    MissingSecurity,
+    /// The security is not in the registry
+    #[error("unknown security {0}, it is not in the registry")]
+    UnknownSecurity(String),
+    /// Neither the account nor its external id have a public key
+    #[error("no public key for account {0}, it is not in the key registry")]
+    UnmappedAccount(String),
+    /// The size cannot be converted to a contract amount
+    #[error(transparent)]
+    Amount(#[from] AmountError),
+}
+
+// report listing every invalid change on its own line
+struct InvalidChanges<'a>(&'a [InvalidChange]);
+
+impl Display for InvalidChanges<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid change(s):", self.0.len())?;
+        for change in self.0 {
+            write!(f, "\n  {change}")?;
+        }
+        Ok(())
+    }
 }
 
+/// Key derived from the phrase, only for accounts without a registered key
 pub fn public_key<T: AsRef<[u8]>>(phrase: T) -> PublicKey {
     let hash = blake3::hash(phrase.as_ref());
 
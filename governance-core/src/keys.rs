@@ -0,0 +1,103 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use dusk_bytes::Serializable;
+use dusk_pki::PublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Public keys of the accounts, by account name or external id
+///
+/// Keys are written as bs58 strings, as printed by the wallet
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "BTreeMap<String, String>")]
+#[serde(into = "BTreeMap<String, String>")]
+pub struct KeyRegistry {
+    accounts: BTreeMap<String, PublicKey>,
+}
+
+impl KeyRegistry {
+    /// Load a key file holding an `[accounts]` table
+    pub fn load_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        #[derive(Deserialize)]
+        struct KeyFile {
+            accounts: KeyRegistry,
+        }
+
+        let content = fs::read_to_string(path)?;
+        let file: KeyFile = toml::from_str(&content)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+        Ok(file.accounts)
+    }
+
+    /// Bind an account to the bs58 encoded public key
+    pub fn insert<T: Into<String>>(
+        &mut self,
+        account: T,
+        key: &str,
+    ) -> Result<(), KeyError> {
+        let account = account.into();
+        let key = decode_key(key)
+            .map_err(|e| KeyError::InvalidKey(account.clone(), e))?;
+        self.accounts.insert(account, key);
+
+        Ok(())
+    }
+
+    /// Public key of the account
+    pub fn get(&self, account: &str) -> Option<PublicKey> {
+        self.accounts.get(account).copied()
+    }
+}
+
+fn decode_key(key: &str) -> Result<PublicKey, String> {
+    let bytes = bs58::decode(key).into_vec().map_err(|e| e.to_string())?;
+    let bytes: [u8; PublicKey::SIZE] = bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("expected 64 bytes, got {}", b.len()))?;
+
+    PublicKey::from_bytes(&bytes).map_err(|e| format!("{e:?}"))
+}
+
+impl TryFrom<BTreeMap<String, String>> for KeyRegistry {
+    type Error = KeyError;
+
+    fn try_from(
+        accounts: BTreeMap<String, String>,
+    ) -> Result<Self, Self::Error> {
+        let mut registry = KeyRegistry::default();
+        for (account, key) in accounts {
+            registry.insert(account, &key)?;
+        }
+
+        Ok(registry)
+    }
+}
+
+impl From<KeyRegistry> for BTreeMap<String, String> {
+    fn from(registry: KeyRegistry) -> Self {
+        registry
+            .accounts
+            .into_iter()
+            .map(|(account, key)| {
+                (account, bs58::encode(key.to_bytes()).into_string())
+            })
+            .collect()
+    }
+}
+
+/// Errors of the key registry
+#[derive(Debug, thiserror::Error)]
+pub enum KeyError {
+    /// The public key of an account is malformed
+    #[error("Invalid public key for {0}: {1}")]
+    InvalidKey(String, String),
+}
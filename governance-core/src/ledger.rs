@@ -0,0 +1,284 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use crate::models::{
+    Cause, Change, ChangeType, Decimal, Event, Events, SecurityDefinition,
+};
+
+/// Holdings of every account per security, `Cash` holds the cash position
+///
+/// The events of each account are folded in order, every state the input
+/// cannot lead to is reported as an issue
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    accounts: BTreeMap<String, Account>,
+    issues: Vec<LedgerIssue>,
+}
+
+/// Holdings of a single account
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Account {
+    pub balances: BTreeMap<SecurityDefinition, Decimal>,
+    /// Amounts held by reservations, pending settlement
+    pub reserved: BTreeMap<SecurityDefinition, Decimal>,
+    /// Balances changed by every event, in order
+    pub history: Vec<Position>,
+}
+
+/// Balance of a security after an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub occurrence: u64,
+    pub security: SecurityDefinition,
+    pub balance: Decimal,
+}
+
+impl Ledger {
+    /// Fold the events of every account into their balances
+    pub fn from_events<'a, I>(accounts: I) -> Self
+    where
+        I: IntoIterator<Item = &'a (String, Events)>,
+    {
+        let mut ledger = Ledger::default();
+
+        for (name, events) in accounts {
+            for (index, event) in events.events.iter().enumerate() {
+                ledger.apply(name, index, event);
+            }
+        }
+
+        ledger
+    }
+
+    /// Holdings of the account
+    pub fn account(&self, name: &str) -> Option<&Account> {
+        self.accounts.get(name)
+    }
+
+    /// Holdings of every account, by name
+    pub fn accounts(&self) -> &BTreeMap<String, Account> {
+        &self.accounts
+    }
+
+    /// Balance of a security held by the account
+    pub fn balance(
+        &self,
+        name: &str,
+        security: &SecurityDefinition,
+    ) -> Decimal {
+        self.account(name)
+            .and_then(|account| account.balances.get(security))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// States the events cannot lead to
+    pub fn issues(&self) -> &[LedgerIssue] {
+        &self.issues
+    }
+
+    /// Fail with every issue found
+    pub fn check(&self) -> Result<(), LedgerError> {
+        if self.issues.is_empty() {
+            Ok(())
+        } else {
+            Err(LedgerError(self.issues.clone()))
+        }
+    }
+
+    // changes of an event happen together, the balances are checked once all
+    // of them are applied
+    fn apply(&mut self, name: &str, index: usize, event: &Event) {
+        let account = self.accounts.entry(name.to_string()).or_default();
+        let mut changed = BTreeSet::new();
+        let mut overdrawn = BTreeSet::new();
+
+        for change in &event.changes {
+            if change.change_type == ChangeType::Reservation {
+                let security = match &change.security {
+                    SecurityDefinition::None => SecurityDefinition::Cash,
+                    security => security.clone(),
+                };
+                let reserved =
+                    account.reserved.entry(security.clone()).or_default();
+
+                // negative sizes hold funds for a pending outflow, positive
+                // sizes release them
+                if change.size > *reserved {
+                    self.issues.push(LedgerIssue {
+                        account: name.to_string(),
+                        event: index,
+                        occurrence: event.occurrence,
+                        security: security.clone(),
+                        kind: IssueKind::OverRelease {
+                            amount: change.size,
+                            reserved: *reserved,
+                        },
+                    });
+                }
+                match reserved.checked_sub(change.size) {
+                    Some(left) => *reserved = left.max(Decimal::ZERO),
+                    None => self.issues.push(LedgerIssue {
+                        account: name.to_string(),
+                        event: index,
+                        occurrence: event.occurrence,
+                        security,
+                        kind: IssueKind::Overflow(change.size),
+                    }),
+                }
+                continue;
+            }
+
+            let Some((security, delta)) = delta(change, &event.cause) else {
+                continue;
+            };
+            let balance = account.balances.entry(security.clone()).or_default();
+
+            // withdrawals and fees leave the account, they need the holdings
+            let outflow = matches!(event.cause, Cause::Withdrawal | Cause::Fee);
+            if outflow && -delta > *balance {
+                self.issues.push(LedgerIssue {
+                    account: name.to_string(),
+                    event: index,
+                    occurrence: event.occurrence,
+                    security: security.clone(),
+                    kind: IssueKind::OverWithdrawal {
+                        amount: -delta,
+                        held: *balance,
+                    },
+                });
+                overdrawn.insert(security.clone());
+            }
+
+            // a settled outflow consumes the funds held for it
+            if outflow {
+                if let Some(reserved) = account.reserved.get_mut(&security) {
+                    *reserved -= (-delta).min(*reserved);
+                }
+            }
+
+            match balance.checked_add(delta) {
+                Some(sum) => *balance = sum,
+                None => self.issues.push(LedgerIssue {
+                    account: name.to_string(),
+                    event: index,
+                    occurrence: event.occurrence,
+                    security: security.clone(),
+                    kind: IssueKind::Overflow(delta),
+                }),
+            }
+            changed.insert(security);
+        }
+
+        for security in changed {
+            let balance = account.balances[&security];
+
+            if balance.is_sign_negative()
+                && !balance.is_zero()
+                && !overdrawn.contains(&security)
+            {
+                self.issues.push(LedgerIssue {
+                    account: name.to_string(),
+                    event: index,
+                    occurrence: event.occurrence,
+                    security: security.clone(),
+                    kind: IssueKind::NegativeBalance(balance),
+                });
+            }
+
+            account.history.push(Position {
+                occurrence: event.occurrence,
+                security,
+                balance,
+            });
+        }
+    }
+}
+
+// security and signed amount moved by a change, following the conversion to
+// transfers
+fn delta(
+    change: &Change,
+    cause: &Cause,
+) -> Option<(SecurityDefinition, Decimal)> {
+    let security = match change.change_type {
+        ChangeType::Reservation => return None,
+        ChangeType::Cash => SecurityDefinition::Cash,
+        ChangeType::Security if change.security == SecurityDefinition::None => {
+            return None
+        }
+        ChangeType::Security => change.security.clone(),
+    };
+
+    let delta = match cause {
+        Cause::Rebalance => change.size,
+        Cause::Deposit => change.size,
+        Cause::Withdrawal | Cause::Fee => -change.size.abs(),
+    };
+
+    Some((security, delta))
+}
+
+/// A state of the ledger the events cannot lead to
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{account}: events[{event}]: {security}: {kind}")]
+pub struct LedgerIssue {
+    pub account: String,
+    /// Index of the event in the events of the account
+    pub event: usize,
+    pub occurrence: u64,
+    pub security: SecurityDefinition,
+    pub kind: IssueKind,
+}
+
+/// Kind of a ledger issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The account holds a negative amount after the event
+    NegativeBalance(Decimal),
+    /// A withdrawal or fee exceeds the holdings of the account
+    OverWithdrawal { amount: Decimal, held: Decimal },
+    /// A reservation releases more than the amount held
+    OverRelease { amount: Decimal, reserved: Decimal },
+    /// The amount moved doesn't fit the holdings, they are left unchanged
+    Overflow(Decimal),
+}
+
+impl Display for IssueKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IssueKind::NegativeBalance(balance) => {
+                write!(f, "negative balance {balance}")
+            }
+            IssueKind::OverWithdrawal { amount, held } => {
+                write!(f, "withdrawal of {amount} exceeds the {held} held")
+            }
+            IssueKind::OverRelease { amount, reserved } => {
+                write!(f, "release of {amount} exceeds the {reserved} reserved")
+            }
+            IssueKind::Overflow(amount) => {
+                write!(f, "{amount} overflows the holdings")
+            }
+        }
+    }
+}
+
+/// The ledger found states the events cannot lead to
+#[derive(Debug, thiserror::Error)]
+pub struct LedgerError(pub Vec<LedgerIssue>);
+
+impl Display for LedgerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ledger issue(s):", self.0.len())?;
+        for issue in &self.0 {
+            write!(f, "\n  {issue}")?;
+        }
+        Ok(())
+    }
+}
@@ -4,39 +4,94 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+// hash-chained log of the payloads broadcast
+pub mod audit;
 // read json data and make sense of it
 pub mod json;
+// read csv exports of the same events
+pub mod csv;
 // config of the backend
 pub mod config;
+// journal of the payloads sent, to resume interrupted runs
+pub mod journal;
+// balances of the accounts rebuilt from the events
+pub mod ledger;
+// consistency of the cash and security legs of rebalances
+pub mod rebalance;
+// contracts of the listed securities
+pub mod registry;
+// public keys of the accounts
+pub mod keys;
+// export, offline signing and submission of the payloads
+pub mod offline;
+// receipts of the batches handled by a run
+pub mod receipt;
+// fingerprints of the batches confirmed by every run
+pub mod sent;
+// counters and latency of the runs, in the Prometheus text format
+pub mod metrics;
+// retries of the transient network errors
+pub mod retry;
+// decoding and verification of signed payloads
+pub mod verify;
 // types/models for the backend
 pub mod models;
 
 // transaction propagation confirmation
 mod gql;
+// broadcasting and status lookup, with a mock node for offline runs
+pub mod transport;
 
 pub mod prelude {
-    pub use crate::config::{Config, SecureWallet};
-    pub use crate::gql::GraphQL;
+    pub use crate::audit::{AuditEntry, AuditError, AuditLog};
+    pub use crate::config::{
+        Config, Network, NetworkError, OverrideError, SecureWallet,
+    };
+    pub use crate::csv::*;
+    pub use crate::gql::{GraphQL, GraphQLError, TxStatus};
+    pub use crate::journal::{Journal, TxState};
     pub use crate::json::*;
+    pub use crate::keys::{KeyError, KeyRegistry};
+    pub use crate::ledger::{
+        Account, IssueKind, Ledger, LedgerError, LedgerIssue, Position,
+    };
+    pub use crate::metrics::Metrics;
     pub use crate::models::*;
-    pub use crate::Governance;
+    pub use crate::offline::{OfflineBatch, OfflineError, OfflineFile};
+    pub use crate::rebalance::{
+        check_rebalances, RebalanceError, RebalanceMismatch,
+    };
+    pub use crate::receipt::{Receipt, ReceiptStatus, Receipts};
+    pub use crate::registry::{ContractRef, Registry, RegistryError};
+    pub use crate::retry::{ErrorClass, Retry, RetryPolicy};
+    pub use crate::sent::{
+        Duplicate, DuplicateError, DuplicatePolicy, Fingerprint, SentRegistry,
+    };
+    pub use crate::transport::{
+        BroadcastError, Broadcaster, MockNode, SignedPayload, StatusProvider,
+        WalletBroadcaster,
+    };
+    pub use crate::verify::{verify_payload, VerifiedPayload, VerifyError};
+    pub use crate::{DryRun, Governance};
 }
 
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use crate::prelude::*;
 
 use blake2::{digest::consts::U32, Digest};
 use canonical::{Canon, EncodeToVec, Sink};
+use chrono::Utc;
 use dusk_abi::ContractId;
 use dusk_bls12_381::BlsScalar;
 use dusk_bls12_381_sign::{
     PublicKey as BlsPublicKey, SecretKey as BlsSecretKey, Signature,
 };
 use dusk_bytes::Serializable;
-use dusk_wallet::{gas::Gas, TransportTCP, Wallet};
+use dusk_wallet::{TransportTCP, Wallet};
 use toml_base_config::BaseConfig;
-use tracing::info;
+use tracing::{info, warn};
 
 pub const TX_TRANSFER: u8 = 0x04;
 pub const TX_FEE: u8 = 0x05;
@@ -44,6 +99,14 @@ pub const TX_FEE: u8 = 0x05;
 pub struct Governance {
     config: Config,
     wallet: SecureWallet,
+    journal: Option<PathBuf>,
+    sent: Option<PathBuf>,
+    audit: Option<PathBuf>,
+    metrics: Metrics,
+    // bs58 public key the payloads are signed with, if known
+    signer: Option<String>,
+    // key the payloads are signed with, when signed by this run
+    key: Option<BlsSecretKey>,
 }
 
 impl Governance {
@@ -55,31 +118,118 @@ impl Governance {
         Ok(Self {
             config: Config::load_path(config)?,
             wallet,
+            journal: None,
+            sent: None,
+            audit: None,
+            metrics: Metrics::default(),
+            signer: None,
+            key: None,
         })
     }
 
+    // Create a new Governance instance with the given config
+    pub fn from_config(wallet: SecureWallet, config: Config) -> Self {
+        Self {
+            config,
+            wallet,
+            journal: None,
+            sent: None,
+            audit: None,
+            metrics: Metrics::default(),
+            signer: None,
+            key: None,
+        }
+    }
+
     // Set a custom config, by default it loads from the config.toml file
     pub fn with_config(&mut self, config: Config) {
         self.config = config;
     }
 
-    /// Data we send to the blockchain
-    pub async fn send_data(self, data: TransferMap) -> anyhow::Result<()> {
-        let Self {
-            wallet,
-            config:
-                Config {
-                    rusk_address,
-                    prover_address,
-                    graphql_address,
-                    gas_limit,
-                    gas_price,
-                },
-        } = self;
+    // Persist the sent payloads in a journal, so a failed run can be resumed
+    // without sending the confirmed ones again
+    pub fn with_journal(&mut self, path: PathBuf) {
+        self.journal = Some(path);
+    }
+
+    // Keep the fingerprints of the confirmed batches, so a batch sent by an
+    // earlier run is handled according to the duplicate policy
+    pub fn with_sent_registry(&mut self, path: PathBuf) {
+        self.sent = Some(path);
+    }
+
+    // Append every payload broadcast to a hash-chained audit log
+    pub fn with_audit_log(&mut self, path: PathBuf) {
+        self.audit = Some(path);
+    }
+
+    // Record the batches sent, their confirmation latency and the retries
+    pub fn with_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
 
-        let mut wallet = Wallet::from_file(wallet)?;
+    /// Data we send to the blockchain, with a receipt per batch
+    pub async fn send_data(
+        self,
+        data: TransferMap,
+    ) -> anyhow::Result<Vec<Receipt>> {
+        let wallet = Wallet::from_file(self.wallet.clone())?;
         let (_, sec_key) = wallet.provisioner_keys(wallet.default_address())?;
-        let transport_tcp = TransportTCP::new(rusk_address, prover_address);
+        let (broadcaster, gql) = self.connect(wallet).await?;
+
+        self.send_with(data, &sec_key, &broadcaster, &gql).await
+    }
+
+    /// Send the payloads of an offline file signed on another host, once
+    /// checked to be signed by the given provisioner key. The wallet only
+    /// pays the gas, it doesn't need to hold the provisioner keys
+    pub async fn submit(
+        self,
+        file: OfflineFile,
+        signer: &BlsPublicKey,
+    ) -> anyhow::Result<Vec<Receipt>> {
+        let wallet = Wallet::from_file(self.wallet.clone())?;
+        let (broadcaster, gql) = self.connect(wallet).await?;
+
+        self.submit_file_with(file, signer, &broadcaster, &gql)
+            .await
+    }
+
+    /// Send the payloads of an offline file through the given transport,
+    /// once checked to be signed by the given provisioner key
+    pub async fn submit_file_with<B, P>(
+        mut self,
+        file: OfflineFile,
+        signer: &BlsPublicKey,
+        broadcaster: &B,
+        status: &P,
+    ) -> anyhow::Result<Vec<Receipt>>
+    where
+        B: Broadcaster + ?Sized,
+        P: StatusProvider + ?Sized,
+    {
+        let signed = file.verified(signer)?;
+        self.signer = Some(bs58::encode(signer.to_bytes()).into_string());
+
+        self.submit_with(signed, broadcaster, status).await
+    }
+
+    // connect the wallet to the node, paying the gas of the transactions
+    async fn connect(
+        &self,
+        mut wallet: Wallet<SecureWallet>,
+    ) -> anyhow::Result<(WalletBroadcaster, GraphQL)> {
+        let Config {
+            rusk_address,
+            prover_address,
+            graphql_address,
+            gas_limit,
+            gas_price,
+            ..
+        } = &self.config;
+
+        let transport_tcp =
+            TransportTCP::new(rusk_address.clone(), prover_address.clone());
 
         wallet
             .connect_with_status(transport_tcp, |s| {
@@ -88,88 +238,321 @@ impl Governance {
             .await?;
 
         assert!(wallet.is_online(), "Wallet is not online");
-        let transfers = data.into_transfers();
-        let gql = GraphQL::new(graphql_address, |s| {
-            tracing::info!(target: "graphql", "{s}",);
-        });
-
-        for (security, (transfers, fees)) in transfers {
-            let security_name = security.to_string();
-            // get contract_id from security
-            let contract_id = security.to_id();
-
-            if !transfers.is_empty() {
-                info!(
-                    "Sending {} transfer(s) for {security_name}",
-                    &transfers.len()
-                );
-                let payload = (seed(&transfers), TX_TRANSFER, transfers);
-                let data = signed_payload(&sec_key, payload);
-
-                let tx_hash =
-                    send(data, &wallet, contract_id, gas_limit, gas_price)
-                        .await?;
-                let tx_id = format!("{:x}", tx_hash);
-                gql.wait_for(&tx_id).await?;
-            };
+        let gql = GraphQL::new(graphql_address.clone())
+            .with_metrics(self.metrics.clone());
+        let broadcaster =
+            WalletBroadcaster::new(wallet, *gas_limit, *gas_price);
+
+        Ok((broadcaster, gql))
+    }
+
+    /// Sign the data and send it through the given transport, waiting for
+    /// every transaction to be confirmed
+    pub async fn send_with<B, P>(
+        mut self,
+        data: TransferMap,
+        sec_key: &BlsSecretKey,
+        broadcaster: &B,
+        status: &P,
+    ) -> anyhow::Result<Vec<Receipt>>
+    where
+        B: Broadcaster + ?Sized,
+        P: StatusProvider + ?Sized,
+    {
+        let pk = BlsPublicKey::from(sec_key);
+        self.signer = Some(bs58::encode(pk.to_bytes()).into_string());
+        self.key = Some(*sec_key);
+
+        let signed = data
+            .into_batches(
+                &self.config.registry()?,
+                &self.config.batch_limits(),
+            )?
+            .into_iter()
+            .map(|batch| {
+                let payload = signed_payload(sec_key, batch.clone().payload());
+                (batch, payload)
+            })
+            .collect();
+
+        self.submit_with(signed, broadcaster, status).await
+    }
+
+    /// Send the signed payloads through the given transport in order,
+    /// waiting for every transaction to be confirmed
+    ///
+    /// On failure the error carries the [`Receipts`] of the run so far
+    pub async fn submit_with<B, P>(
+        self,
+        signed: Vec<(Batch, SignedPayload)>,
+        broadcaster: &B,
+        status: &P,
+    ) -> anyhow::Result<Vec<Receipt>>
+    where
+        B: Broadcaster + ?Sized,
+        P: StatusProvider + ?Sized,
+    {
+        let metrics = &self.metrics;
+        let broadcaster = &Retry::new(broadcaster, &self.config.retry, metrics);
+        let status = &Retry::new(status, &self.config.retry, metrics);
+        let timeout = Duration::from_secs(self.config.confirmation_timeout);
+        let interval = Duration::from_millis(self.config.poll_interval);
+        let wait_for = |tx_id: String| async move {
+            transport::wait_for(status, &tx_id, timeout, interval, |s| {
+                info!(target: "graphql", "{s}",);
+            })
+            .await
+        };
+
+        let mut journal = match self.journal {
+            Some(path) => Journal::open(path)?,
+            None => Journal::default(),
+        };
+        let mut sent = match self.sent {
+            Some(path) => SentRegistry::open(path)?,
+            None => SentRegistry::default(),
+        };
+        let mut audit = match self.audit {
+            Some(path) => {
+                let mut audit = AuditLog::open(path, &sent)?;
+                if let Some(sk) = self.key {
+                    audit.with_key(sk);
+                }
+                Some(audit)
+            }
+            None => None,
+        };
+        let network = self.config.network.as_deref();
+        let signer = self.signer.as_deref();
+        let policy = self.config.duplicates;
+        let mut receipts = vec![];
+
+        // duplicates are batches confirmed by the earlier runs, with whether
+        // they were confirmed in full. The batches confirmed by this run
+        // don't count, identical chunks are all sent.
+        let earlier: Vec<_> = signed
+            .iter()
+            .map(|(batch, _)| {
+                let prev = sent.get(batch)?.clone();
+                Some((prev, sent.covers(batch)))
+            })
+            .collect();
+
+        // batches of an interrupted run are in the journal, not duplicates
+        if policy == DuplicatePolicy::Refuse {
+            let duplicates: Vec<_> = signed
+                .iter()
+                .zip(&earlier)
+                .filter(|((batch, _), _)| journal.get(batch).is_none())
+                .filter_map(|((batch, _), earlier)| {
+                    let (prev, _) = earlier.as_ref()?;
+                    Some(Duplicate::new(batch, prev))
+                })
+                .collect();
+
+            if !duplicates.is_empty() {
+                return Err(DuplicateError(duplicates).into());
+            }
+        }
+
+        for ((batch, data), earlier) in signed.into_iter().zip(earlier) {
+            let name = format!("{}(s) for {}", batch.kind, batch.security);
+            let mut receipt = Receipt::new(&batch, ReceiptStatus::Confirmed);
+
+            match journal.get(&batch) {
+                Some(entry) if entry.state == TxState::Confirmed => {
+                    info!("Skipping confirmed {name}");
+                    receipt.status = ReceiptStatus::Skipped;
+                    receipt.tx_hash = Some(entry.tx_hash.clone());
+                    receipts.push(receipt);
+                    continue;
+                }
+                Some(entry) => {
+                    let tx_id = entry.tx_hash.clone();
+                    info!("Resuming submitted {name} in {tx_id}");
+
+                    match wait_for(tx_id.clone()).await {
+                        Ok(block_height) => {
+                            journal.confirmed(&batch)?;
+                            if sent.get(&batch).is_none() {
+                                sent.confirmed(&batch, &tx_id)?;
+                            }
+                            receipt.tx_hash = Some(tx_id);
+                            receipt.block_height = block_height;
+                            receipts.push(receipt);
+                            continue;
+                        }
+                        Err(err @ GraphQLError::Transaction(_)) => {
+                            warn!("Sending {name} again: {err}")
+                        }
+                        // the transaction may still be pending or included,
+                        // sending it again could book the batch twice
+                        Err(err) => {
+                            metrics.batch_failed(&batch);
+                            receipt.status = ReceiptStatus::Failed;
+                            receipt.tx_hash = Some(tx_id);
+                            receipt.error = Some(err.to_string());
+                            receipts.push(receipt);
 
-            if !fees.is_empty() {
-                info!("Sending {} fee(s) for {security_name}", &fees.len());
-                let payload = (seed(&fees), TX_FEE, fees);
-                let data = signed_payload(&sec_key, payload);
+                            let err = anyhow::Error::from(err);
+                            return Err(err.context(Receipts(receipts)));
+                        }
+                    }
+                }
+                None => match (earlier, policy) {
+                    (Some((prev, true)), DuplicatePolicy::Skip) => {
+                        info!("Skipping {name} sent in {}", prev.tx_hash);
+                        receipt.status = ReceiptStatus::Skipped;
+                        receipt.tx_hash = Some(prev.tx_hash.clone());
+                        receipts.push(receipt);
+                        continue;
+                    }
+                    // skipping the batch would leave out the transfers that
+                    // were never sent
+                    (Some((prev, false)), DuplicatePolicy::Skip) => {
+                        let err =
+                            DuplicateError(vec![Duplicate::new(&batch, &prev)]);
+                        let err = anyhow::Error::from(err);
+                        return Err(err.context(Receipts(receipts)));
+                    }
+                    (Some((prev, _)), _) => {
+                        warn!("Sending {name} again, sent in {}", prev.tx_hash)
+                    }
+                    (None, _) => (),
+                },
+            }
+
+            info!("Sending {} {name}", batch.transfers.len());
+            receipt.submitted_at = Some(Utc::now().to_rfc3339());
+            let start = Instant::now();
 
-                let tx_hash =
-                    send(data, &wallet, contract_id, gas_limit, gas_price)
-                        .await?;
+            let res = async {
+                let tx_hash = broadcaster
+                    .execute(batch.contract_id, data.clone())
+                    .await?;
                 let tx_id = format!("{:x}", tx_hash);
-                gql.wait_for(&tx_id).await?;
+                metrics.batch_sent(&batch);
+                receipt.tx_hash = Some(tx_id.clone());
+                journal.submitted(&batch, &tx_id)?;
+                if let Some(audit) = &mut audit {
+                    audit.record(network, &batch, &data, signer, &tx_id)?;
+                    sent.audited(audit.head())?;
+                }
+
+                receipt.block_height = wait_for(tx_id.clone()).await?;
+                let latency = start.elapsed();
+                receipt.confirmation_ms = Some(latency.as_millis() as u64);
+                metrics.confirmed(latency);
+                journal.confirmed(&batch)?;
+                sent.confirmed(&batch, &tx_id)?;
+
+                anyhow::Ok(())
             }
+            .await;
+
+            if let Err(err) = res {
+                metrics.batch_failed(&batch);
+                receipt.status = ReceiptStatus::Failed;
+                receipt.error = Some(err.to_string());
+                receipts.push(receipt);
+
+                return Err(err.context(Receipts(receipts)));
+            }
+            receipts.push(receipt);
         }
-        Ok(())
+
+        journal.finish()?;
+        Ok(receipts)
     }
-}
 
-// send to the blockchain
-async fn send<C>(
-    data: C,
-    wallet: &Wallet<SecureWallet>,
-    contract_id: ContractId,
-    gas_limit: u64,
-    gas_price: Option<u64>,
-) -> Result<BlsScalar, dusk_wallet::Error>
-where
-    C: Canon,
-{
-    // TODO: Make sure this is correct
-    let sender = wallet.default_address();
+    /// Sign the payloads of an offline file with the provisioner key,
+    /// without connecting to the network
+    pub fn sign(self, file: &mut OfflineFile) -> anyhow::Result<()> {
+        let wallet = Wallet::from_file(self.wallet)?;
+        let (_, sec_key) = wallet.provisioner_keys(wallet.default_address())?;
 
-    let mut gas = Gas::new(gas_limit);
-    gas.set_price(gas_price);
+        Ok(file.sign(&sec_key)?)
+    }
 
-    // finish sending data to blockchain
-    let tx = wallet.execute(sender, contract_id, data, gas).await?;
+    /// Sign the data we would send to the blockchain, without connecting to
+    /// the network
+    pub fn dry_run(self, data: TransferMap) -> anyhow::Result<Vec<DryRun>> {
+        let wallet = Wallet::from_file(self.wallet.clone())?;
+        let (_, sec_key) = wallet.provisioner_keys(wallet.default_address())?;
+
+        self.dry_run_with(data, &sec_key)
+    }
+
+    /// Sign the data with the given key, nothing is sent nor recorded
+    pub fn dry_run_with(
+        self,
+        data: TransferMap,
+        sec_key: &BlsSecretKey,
+    ) -> anyhow::Result<Vec<DryRun>> {
+        let dry_run = data
+            .into_batches(
+                &self.config.registry()?,
+                &self.config.batch_limits(),
+            )?
+            .into_iter()
+            .map(|batch| DryRun {
+                security: batch.security.clone(),
+                kind: batch.kind,
+                count: batch.transfers.len(),
+                contract_id: batch.contract_id,
+                payload: signed_payload(sec_key, batch.payload())
+                    .encode_to_vec(),
+            })
+            .collect();
 
-    Ok(tx.hash())
+        Ok(dry_run)
+    }
+}
+
+/// Signed payload of a batch that was not sent to the blockchain
+#[derive(Debug)]
+pub struct DryRun {
+    pub security: SecurityDefinition,
+    pub kind: BatchKind,
+    /// Number of transfers in the payload
+    pub count: usize,
+    pub contract_id: ContractId,
+    /// Canonical encoding of the signed payload
+    pub payload: Vec<u8>,
 }
 
 // sign the payload before sending to the blockchain
-fn signed_payload<C>(sk: &BlsSecretKey, payload: C) -> (Signature, u32, C)
+pub(crate) fn signed_payload<C>(
+    sk: &BlsSecretKey,
+    payload: C,
+) -> (Signature, u32, C)
 where
     C: Canon,
 {
-    let payload_len = payload.encoded_len();
-    let capacity = payload_len + (payload_len as u32).encoded_len();
-    let len_u32 = capacity as u32;
-    let mut buffer = vec![0; capacity];
+    let buffer = signed_buffer(&payload);
+
+    let pk = BlsPublicKey::from(sk);
+    let signature = sk.sign(&pk, &buffer);
+
+    (signature, buffer.len() as u32, payload)
+}
+
+// buffer covered by the signature, the payload prefixed by its length
+pub(crate) fn signed_buffer<C: Canon>(payload: &C) -> Vec<u8> {
+    let len_u32 = payload_len(payload);
+    let mut buffer = vec![0; len_u32 as usize];
 
     let mut sink = Sink::new(&mut buffer);
     len_u32.encode(&mut sink);
     payload.encode(&mut sink);
 
-    let pk = BlsPublicKey::from(sk);
-    let signature = sk.sign(&pk, &buffer);
+    buffer
+}
 
-    (signature, len_u32, payload)
+// length of the signed buffer, the payload prefixed by the length itself
+pub(crate) fn payload_len<C: Canon>(payload: &C) -> u32 {
+    let payload_len = payload.encoded_len();
+    (payload_len + (payload_len as u32).encoded_len()) as u32
 }
 
 // generate seed for Transfer
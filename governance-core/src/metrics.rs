@@ -0,0 +1,169 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::Batch;
+use crate::retry::ErrorClass;
+
+const BATCHES: &str = "governance_batches_sent_total";
+const TRANSFERS: &str = "governance_transfers_sent_total";
+const FAILURES: &str = "governance_batch_failures_total";
+const RETRIES: &str = "governance_retries_total";
+const QUERIES: &str = "governance_status_queries_total";
+const LATENCY: &str = "governance_confirmation_seconds";
+
+/// Name and help of the counters, in the order they are rendered
+const COUNTERS: &[(&str, &str)] = &[
+    (BATCHES, "Batches broadcast to the contracts"),
+    (TRANSFERS, "Transfers broadcast to the contracts"),
+    (FAILURES, "Batches that could not be sent or confirmed"),
+    (RETRIES, "Calls to the node retried after a transient error"),
+    (
+        QUERIES,
+        "Transaction status queries to the GraphQL endpoint",
+    ),
+];
+
+/// Upper bounds in seconds of the confirmation latency buckets
+const BUCKETS: &[f64] = &[0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// Counters and confirmation latency of a run, rendered in the Prometheus
+/// text format. Clones share the same values.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics(Arc<Mutex<Values>>);
+
+#[derive(Debug, Default)]
+struct Values {
+    // counter name, then rendered labels
+    counters: BTreeMap<&'static str, BTreeMap<String, u64>>,
+    latency: Histogram,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A batch was broadcast
+    pub fn batch_sent(&self, batch: &Batch) {
+        let security = batch.security.to_string();
+        let kind = batch.kind.to_string();
+        let transfers = batch.transfers.len() as u64;
+
+        self.add(BATCHES, &[("security", &security), ("kind", &kind)], 1);
+        self.add(TRANSFERS, &[("security", &security)], transfers);
+    }
+
+    /// A batch failed to be sent or confirmed
+    pub fn batch_failed(&self, batch: &Batch) {
+        let security = batch.security.to_string();
+        self.add(FAILURES, &[("security", &security)], 1);
+    }
+
+    /// A transaction was confirmed, `latency` after being sent
+    pub fn confirmed(&self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        let latency = &mut self.0.lock().unwrap().latency;
+
+        BUCKETS
+            .iter()
+            .zip(latency.buckets.iter_mut())
+            .filter(|(bound, _)| seconds <= **bound)
+            .for_each(|(_, bucket)| *bucket += 1);
+        latency.sum += seconds;
+        latency.count += 1;
+    }
+
+    /// A call is retried after an error of the class
+    pub fn retried(&self, class: ErrorClass) {
+        let class = format!("{class:?}").to_lowercase();
+        self.add(RETRIES, &[("class", &class)], 1);
+    }
+
+    /// A status query returned, `result` being `ok`, `not_found` or `error`
+    pub fn status_query(&self, result: &str) {
+        self.add(QUERIES, &[("result", result)], 1);
+    }
+
+    fn add(&self, name: &'static str, labels: &[(&str, &str)], value: u64) {
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let mut values = self.0.lock().unwrap();
+        *values
+            .counters
+            .entry(name)
+            .or_default()
+            .entry(labels)
+            .or_default() += value;
+    }
+
+    /// Every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let values = self.0.lock().unwrap();
+        let mut out = String::new();
+
+        for (name, help) in COUNTERS {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (labels, value) in
+                values.counters.get(name).into_iter().flatten()
+            {
+                let _ = writeln!(out, "{name}{{{labels}}} {value}");
+            }
+        }
+
+        let latency = &values.latency;
+        let _ = writeln!(
+            out,
+            "# HELP {LATENCY} Time from sending a batch to its confirmation"
+        );
+        let _ = writeln!(out, "# TYPE {LATENCY} histogram");
+        for (bound, count) in BUCKETS.iter().zip(latency.buckets) {
+            let _ = writeln!(out, "{LATENCY}_bucket{{le=\"{bound}\"}} {count}");
+        }
+        let _ =
+            writeln!(out, "{LATENCY}_bucket{{le=\"+Inf\"}} {}", latency.count);
+        let _ = writeln!(out, "{LATENCY}_sum {}", latency.sum);
+        let _ = writeln!(out, "{LATENCY}_count {}", latency.count);
+
+        out
+    }
+
+    /// Write the metrics for the textfile collector, through a temporary
+    /// file so the collector never reads a partial file
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+
+        fs::write(&tmp, self.render())?;
+        fs::rename(tmp, path)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -4,20 +4,26 @@
 //
 // Copyright (c) DUSK NETWORK. All rights reserved.
 
+pub mod batch;
 pub mod events;
 pub mod transfer;
 
+pub use self::batch::*;
 pub use self::events::*;
 pub use self::transfer::*;
+pub use rust_decimal::Decimal;
 
 use std::collections::HashMap;
+
+use crate::registry::Registry;
+
 /// Type of the hashmap we use to store our Transfers
 /// The first element of the tuple is the deposit transfers and the second is
 /// the fee transfers
 pub type TxHashMap =
     HashMap<SecurityDefinition, (Vec<Transfer>, Vec<Transfer>)>;
 /// List of transfers we send to the blockchain
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct TransferMap {
     transfers: TxHashMap,
 }
@@ -42,4 +48,43 @@ impl TransferMap {
     pub fn into_transfers(self) -> TxHashMap {
         self.transfers
     }
+
+    /// Split the map into the batches sent to the blockchain, the transfers
+    /// of a security come before its fees. Vectors exceeding the limits are
+    /// split into several batches, each with its own seed.
+    pub fn into_batches(
+        self,
+        registry: &Registry,
+        limits: &BatchLimits,
+    ) -> Result<Vec<Batch>, BatchError> {
+        let mut batches = vec![];
+
+        for (security, (transfers, fees)) in self.transfers {
+            let contract_id = registry.contract_id(&security)?;
+            let kinds =
+                [(BatchKind::Transfer, transfers), (BatchKind::Fee, fees)];
+            for (kind, transfers) in kinds {
+                for transfers in limits.split(transfers)? {
+                    let repeat = batches
+                        .iter()
+                        .filter(|batch: &&Batch| {
+                            batch.contract_id == contract_id
+                                && batch.kind == kind
+                                && batch.transfers == transfers
+                        })
+                        .count();
+
+                    batches.push(Batch {
+                        security: security.clone(),
+                        contract_id,
+                        kind,
+                        transfers,
+                        repeat,
+                    });
+                }
+            }
+        }
+
+        Ok(batches)
+    }
 }
@@ -0,0 +1,192 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use canonical::Canon;
+use dusk_abi::ContractId;
+use dusk_bls12_381::BlsScalar;
+use dusk_bls12_381_sign::Signature;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tai64::Tai64;
+
+use crate::models::{
+    SecurityDefinition, Transfer, FIXED_SCALE, MAX_FRACTIONAL_DIGITS,
+};
+use crate::registry::RegistryError;
+use crate::transport::SignedPayload;
+use crate::{seed, TX_FEE, TX_TRANSFER};
+
+/// Payload we sign and send to the contract: seed, operation and transfers
+pub type Payload = (BlsScalar, u8, Vec<Transfer>);
+
+/// Kind of the transfers held by a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchKind {
+    Transfer,
+    Fee,
+}
+
+impl BatchKind {
+    /// Operation code understood by the governance contract
+    pub fn op(&self) -> u8 {
+        match self {
+            BatchKind::Transfer => TX_TRANSFER,
+            BatchKind::Fee => TX_FEE,
+        }
+    }
+
+    /// Kind of the batch with the given operation code
+    pub fn from_op(op: u8) -> Option<Self> {
+        match op {
+            TX_TRANSFER => Some(BatchKind::Transfer),
+            TX_FEE => Some(BatchKind::Fee),
+            _ => None,
+        }
+    }
+}
+
+impl Display for BatchKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        let x = match self {
+            BatchKind::Transfer => "transfer",
+            BatchKind::Fee => "fee",
+        };
+
+        write!(f, "{x}")
+    }
+}
+
+/// Transfers of one kind sent to the contract of a security in a single
+/// transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub security: SecurityDefinition,
+    /// Contract the batch is sent to
+    pub contract_id: ContractId,
+    pub kind: BatchKind,
+    pub transfers: Vec<Transfer>,
+    /// Earlier batches of the run with the same contract, kind and
+    /// transfers, telling identical chunks apart as they share the seed
+    pub repeat: usize,
+}
+
+impl Batch {
+    /// Seed identifying the transfers of the batch
+    pub fn seed(&self) -> BlsScalar {
+        seed(&self.transfers)
+    }
+
+    /// Sum of the amounts of the transfers, converted back from the
+    /// fixed-point format
+    pub fn total(&self) -> Decimal {
+        let total: u128 =
+            self.transfers.iter().map(|tx| u128::from(tx.amount)).sum();
+        (Decimal::from(total) / Decimal::from(FIXED_SCALE))
+            .round_dp(MAX_FRACTIONAL_DIGITS)
+    }
+
+    /// Unsigned payload of the batch
+    pub fn payload(self) -> Payload {
+        (seed(&self.transfers), self.kind.op(), self.transfers)
+    }
+}
+
+/// Upper bounds of a single batch, a vector of transfers exceeding them is
+/// split into several batches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Maximum number of transfers in a batch
+    pub max_transfers: Option<usize>,
+    /// Maximum size in bytes of the encoded signed payload of a batch, as
+    /// sent to the contract
+    pub max_bytes: Option<usize>,
+    /// Never mix transfers of different calendar days (UTC) in a batch
+    pub by_day: bool,
+}
+
+impl BatchLimits {
+    /// Split the transfers into chunks within the limits, in order, the
+    /// days in chronological order when split by day.
+    /// A transfer that can't fit in `max_bytes` on its own is an error.
+    pub fn split(
+        &self,
+        transfers: Vec<Transfer>,
+    ) -> Result<Vec<Vec<Transfer>>, BatchError> {
+        if !self.by_day {
+            return self.chunks(transfers);
+        }
+
+        let mut days: BTreeMap<i64, Vec<Transfer>> = BTreeMap::new();
+        for tx in transfers {
+            let day = Tai64(tx.timestamp).to_unix().div_euclid(86_400);
+            days.entry(day).or_default().push(tx);
+        }
+
+        let mut chunks = vec![];
+        for transfers in days.into_values() {
+            chunks.extend(self.chunks(transfers)?);
+        }
+        Ok(chunks)
+    }
+
+    // chunks of the transfers within the size limits
+    fn chunks(
+        &self,
+        transfers: Vec<Transfer>,
+    ) -> Result<Vec<Vec<Transfer>>, BatchError> {
+        // signature, length prefix and payload without transfers
+        let empty: SignedPayload =
+            (Signature::default(), 0, (BlsScalar::default(), 0, vec![]));
+        let overhead = empty.encoded_len();
+        let max_transfers = self.max_transfers.unwrap_or(usize::MAX).max(1);
+        let max_bytes = self.max_bytes.unwrap_or(usize::MAX);
+
+        let mut chunks = vec![];
+        let mut chunk: Vec<Transfer> = vec![];
+        let mut bytes = overhead;
+
+        for tx in transfers {
+            let len = tx.encoded_len();
+            if overhead.saturating_add(len) > max_bytes {
+                return Err(BatchError::TooLarge {
+                    bytes: overhead + len,
+                    max: max_bytes,
+                });
+            }
+
+            let full = chunk.len() >= max_transfers
+                || bytes.saturating_add(len) > max_bytes;
+            if full && !chunk.is_empty() {
+                chunks.push(std::mem::take(&mut chunk));
+                bytes = overhead;
+            }
+
+            bytes = bytes.saturating_add(len);
+            chunk.push(tx);
+        }
+
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+
+        Ok(chunks)
+    }
+}
+
+/// Errors splitting the transfers into batches
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    /// A security of the transfers is not registered
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    /// A payload holding a single transfer exceeds the size limit
+    #[error("A payload of one transfer needs {bytes} bytes, over {max}")]
+    TooLarge { bytes: usize, max: usize },
+}
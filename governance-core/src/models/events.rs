@@ -9,7 +9,8 @@ use std::fmt::{self, Display, Formatter};
 use canonical::Canon;
 use canonical_derive::Canon;
 use chrono::prelude::*;
-use dusk_abi::ContractId;
+use rust_decimal::Decimal;
+use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use tai64::Tai64;
 
@@ -40,10 +41,12 @@ pub struct Change {
     pub account_external_id: String,
     #[serde(rename = "type")]
     pub change_type: ChangeType,
-    pub size: f32,
+    #[serde(deserialize_with = "to_decimal")]
+    pub size: Decimal,
     #[serde(rename = "securityDefinition")]
     pub security: SecurityDefinition,
-    pub price: f32,
+    #[serde(deserialize_with = "to_decimal")]
+    pub price: Decimal,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
@@ -53,26 +56,23 @@ pub enum ChangeType {
     Reservation,
 }
 
-// Contract IDs are specified for each Security as they are smart contracts
-#[derive(Debug, Clone, Canon, Hash, PartialEq, Eq, Deserialize)]
+// Securities are smart contracts, the contract of each ticker is specified
+// in the Registry
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(from = "String")]
 pub enum SecurityDefinition {
-    Cash = 0x1000,
-    #[serde(rename = "TSWE")]
-    Tswe = 0x1001,
-    #[serde(rename = "TRET")]
-    Tret = 0x1002,
-    #[serde(rename = "TGBT")]
-    Tgbt = 0x1003,
-    #[serde(rename = "TCBT")]
-    Tcbt = 0x1004,
-    None = 0x000,
+    Cash,
+    Ticker(String),
+    None,
 }
 
-impl SecurityDefinition {
-    pub fn to_id(self) -> ContractId {
-        let mut data = [0u8; 32];
-        data[24..].copy_from_slice(&(self as u64).to_be_bytes());
-        ContractId::from(data)
+impl From<String> for SecurityDefinition {
+    fn from(ticker: String) -> Self {
+        match ticker.as_str() {
+            "Cash" => SecurityDefinition::Cash,
+            "None" => SecurityDefinition::None,
+            _ => SecurityDefinition::Ticker(ticker),
+        }
     }
 }
 
@@ -82,10 +82,7 @@ impl Display for SecurityDefinition {
 
         let x = match self {
             Cash => "Cash",
-            Tswe => "TSWE",
-            Tret => "TRET",
-            Tgbt => "TGBT",
-            Tcbt => "TCBT",
+            Ticker(ticker) => ticker,
             None => "None",
         };
 
@@ -93,7 +90,9 @@ impl Display for SecurityDefinition {
     }
 }
 
-fn to_tai64_timestamp<'de, D>(deserializer: D) -> Result<u64, D::Error>
+pub(crate) fn to_tai64_timestamp<'de, D>(
+    deserializer: D,
+) -> Result<u64, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -103,3 +102,67 @@ where
 
     Ok(timestamp.0)
 }
+
+/// Deserialize a number into a decimal, exactly as it is written in the input
+pub(crate) fn to_decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(DecimalVisitor)
+}
+
+/// Deserialize a string field into a decimal, used for formats without
+/// numeric types such as csv
+pub(crate) fn to_decimal_str<'de, D>(
+    deserializer: D,
+) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(DecimalVisitor)
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a decimal number")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimal, E> {
+        let decimal = if v.contains(['e', 'E']) {
+            Decimal::from_scientific(v)
+        } else {
+            Decimal::from_str_exact(v)
+        };
+
+        decimal.map_err(|e| E::custom(format!("invalid decimal {v}: {e}")))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Decimal, E> {
+        // the shortest representation that round-trips is the input text
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Decimal, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        // serde_json with arbitrary_precision hands numbers over as a map
+        // holding their text
+        match map.next_entry::<String, String>()? {
+            Some((_, v)) => self.visit_str(&v),
+            None => Err(de::Error::invalid_length(0, &self)),
+        }
+    }
+}
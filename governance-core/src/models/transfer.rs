@@ -6,6 +6,15 @@
 
 use canonical_derive::Canon;
 use dusk_pki::PublicKey;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// Scale of the fixed-point amounts of the contract, 2^32 - 1
+pub(crate) const FIXED_SCALE: u64 = 4_294_967_295;
+
+/// Fractional digits the fixed-point format can tell apart, its resolution
+/// being about 2.3e-10
+pub(crate) const MAX_FRACTIONAL_DIGITS: u32 = 9;
 
 // TODO: The same struct exists in the governance contract, do we just import
 // that?
@@ -18,21 +27,30 @@ pub struct Transfer {
 }
 
 impl Transfer {
-    pub fn new(amount: f32, timestamp: u64) -> Self {
-        let amount = float2fixed(amount);
+    /// Transfer of the amount converted to the fixed-point format, its
+    /// fractional units truncated
+    pub fn new(amount: Decimal, timestamp: u64) -> Result<Self, AmountError> {
+        let amount = decimal2fixed(amount)?;
 
-        Self {
+        Ok(Self {
             to: None,
             from: None,
             amount,
             timestamp,
-        }
+        })
     }
 
-    pub fn amount(&mut self, amount: f32) -> Self {
-        self.amount = float2fixed(amount);
+    pub fn amount(&mut self, amount: Decimal) -> Result<Self, AmountError> {
+        self.amount = decimal2fixed(amount)?;
 
-        *self
+        Ok(*self)
+    }
+
+    /// Amount converted back from the fixed-point format, to the digits
+    /// accepted by [`Transfer::new`]
+    pub fn decimal_amount(&self) -> Decimal {
+        (Decimal::from(self.amount) / Decimal::from(FIXED_SCALE))
+            .round_dp(MAX_FRACTIONAL_DIGITS)
     }
 
     pub fn withdraw(&mut self, from: PublicKey) -> Self {
@@ -50,7 +68,37 @@ impl Transfer {
     }
 }
 
-fn float2fixed(x: f32) -> u64 {
-    // 2^32 - 1 = 4_294_967_295
-    (x * 4_294_967_295.0) as u64
+/// Errors converting an amount to the fixed-point format of the contract
+#[derive(Debug, thiserror::Error)]
+pub enum AmountError {
+    /// The contract only stores unsigned amounts
+    #[error("amount {0} is negative")]
+    Negative(Decimal),
+    /// The amount does not fit in the fixed-point format
+    #[error("amount {0} is out of the fixed-point range")]
+    OutOfRange(Decimal),
+    /// The amount has more fractional digits than the fixed-point format
+    /// can hold
+    #[error("amount {0} is too precise for the fixed-point format")]
+    Precision(Decimal),
+}
+
+// most decimal fractions have no exact fixed-point value, e.g. 0.1 is
+// 429_496_729.5 units: the contract expects the units truncated toward zero,
+// as the float conversion it was designed against did. An amount needing
+// more digits than the format can tell apart is rejected rather than
+// truncated.
+fn decimal2fixed(x: Decimal) -> Result<u64, AmountError> {
+    if x < Decimal::ZERO {
+        return Err(AmountError::Negative(x));
+    }
+    if x.normalize().scale() > MAX_FRACTIONAL_DIGITS {
+        return Err(AmountError::Precision(x));
+    }
+
+    // any non-zero amount within the digits is at least one fixed-point
+    // unit, and truncating loses less than one unit
+    x.checked_mul(Decimal::from(FIXED_SCALE))
+        .and_then(|fixed| fixed.trunc().to_u64())
+        .ok_or(AmountError::OutOfRange(x))
 }
@@ -0,0 +1,226 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use canonical::{Canon, EncodeToVec, Source};
+use dusk_abi::ContractId;
+use dusk_bls12_381_sign::{
+    PublicKey as BlsPublicKey, SecretKey as BlsSecretKey, Signature,
+};
+use dusk_bytes::Serializable;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Batch, BatchKind, Payload, SecurityDefinition};
+use crate::transport::SignedPayload;
+use crate::verify::{verify_payload, VerifyError};
+use crate::{payload_len, signed_payload};
+
+/// Batches exported to be signed on another host, and later submitted
+///
+/// Every binary field is hex encoded
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OfflineFile {
+    /// bs58 encoded public key of the provisioner, once signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    pub batches: Vec<OfflineBatch>,
+}
+
+/// A batch in the offline file, the signature is missing until it is signed
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OfflineBatch {
+    pub security: String,
+    /// Contract the batch is sent to
+    pub contract: String,
+    pub op: u8,
+    pub seed: String,
+    /// Number of transfers in the payload
+    pub count: usize,
+    /// See [`Batch::repeat`]
+    #[serde(default)]
+    pub repeat: usize,
+    /// Canonical encoding of the unsigned payload
+    pub payload: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl OfflineFile {
+    /// Export the unsigned payloads of the batches
+    pub fn from_batches<I: IntoIterator<Item = Batch>>(batches: I) -> Self {
+        let batches = batches
+            .into_iter()
+            .map(|batch| OfflineBatch {
+                security: batch.security.to_string(),
+                contract: hex::encode(batch.contract_id.as_bytes()),
+                op: batch.kind.op(),
+                seed: hex::encode(batch.seed().to_bytes()),
+                count: batch.transfers.len(),
+                repeat: batch.repeat,
+                signature: None,
+                payload: hex::encode(batch.payload().encode_to_vec()),
+            })
+            .collect();
+
+        Self {
+            signer: None,
+            batches,
+        }
+    }
+
+    /// Read an offline file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, OfflineError> {
+        let content = fs::read(path)?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Write the offline file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), OfflineError> {
+        let content = serde_json::to_vec_pretty(self)?;
+        Ok(fs::write(path, content)?)
+    }
+
+    /// Sign every payload of the file with the provisioner key
+    pub fn sign(&mut self, sk: &BlsSecretKey) -> Result<(), OfflineError> {
+        for (index, offline) in self.batches.iter_mut().enumerate() {
+            let batch = offline
+                .batch()
+                .map_err(|reason| OfflineError::Malformed { index, reason })?;
+            let (signature, _, _) = signed_payload(sk, batch.payload());
+
+            offline.signature = Some(hex::encode(signature.to_bytes()));
+        }
+
+        let pk = BlsPublicKey::from(sk);
+        self.signer = Some(bs58::encode(pk.to_bytes()).into_string());
+
+        Ok(())
+    }
+
+    /// Signed payloads ready to be sent, in the order of the file
+    pub fn signed(&self) -> Result<Vec<(Batch, SignedPayload)>, OfflineError> {
+        self.batches
+            .iter()
+            .enumerate()
+            .map(|(index, offline)| {
+                let signature = offline
+                    .signature
+                    .as_deref()
+                    .ok_or(OfflineError::Unsigned(index))?;
+                let malformed =
+                    |reason| OfflineError::Malformed { index, reason };
+
+                let batch = offline.batch().map_err(malformed)?;
+                let signature =
+                    decode_signature(signature).map_err(malformed)?;
+                let payload = batch.clone().payload();
+
+                Ok((batch, (signature, payload_len(&payload), payload)))
+            })
+            .collect()
+    }
+
+    /// Signed payloads ready to be sent, checked to be signed by the
+    /// provisioner key
+    pub fn verified(
+        &self,
+        pk: &BlsPublicKey,
+    ) -> Result<Vec<(Batch, SignedPayload)>, OfflineError> {
+        let expected = bs58::encode(pk.to_bytes()).into_string();
+        if let Some(signer) = self.signer.as_ref().filter(|s| **s != expected) {
+            return Err(OfflineError::Signer(signer.clone()));
+        }
+
+        let signed = self.signed()?;
+        for (index, (_, payload)) in signed.iter().enumerate() {
+            verify_payload(&payload.encode_to_vec(), pk)
+                .map_err(|source| OfflineError::Signature { index, source })?;
+        }
+
+        Ok(signed)
+    }
+}
+
+impl OfflineBatch {
+    /// Batch held by the payload, checked against the other fields
+    pub fn batch(&self) -> Result<Batch, String> {
+        let contract: [u8; 32] = hex::decode(&self.contract)
+            .map_err(|e| format!("contract: {e}"))?
+            .try_into()
+            .map_err(|_| String::from("contract: expected 32 bytes"))?;
+        let kind = BatchKind::from_op(self.op)
+            .ok_or_else(|| format!("unknown op code {}", self.op))?;
+
+        let bytes =
+            hex::decode(&self.payload).map_err(|e| format!("payload: {e}"))?;
+        let (seed, op, transfers) = Payload::decode(&mut Source::new(&bytes))
+            .map_err(|e| format!("payload: {e:?}"))?;
+
+        let batch = Batch {
+            security: SecurityDefinition::from(self.security.clone()),
+            contract_id: ContractId::from(contract),
+            kind,
+            transfers,
+            repeat: self.repeat,
+        };
+
+        if op != self.op {
+            return Err(format!(
+                "payload op code {op} differs from {}",
+                self.op
+            ));
+        }
+        if batch.seed() != seed
+            || hex::encode(seed.to_bytes()) != self.seed.to_lowercase()
+        {
+            return Err(String::from("the seed does not match the transfers"));
+        }
+        if batch.transfers.len() != self.count {
+            return Err(format!(
+                "expected {} transfer(s), found {}",
+                self.count,
+                batch.transfers.len()
+            ));
+        }
+
+        Ok(batch)
+    }
+}
+
+fn decode_signature(signature: &str) -> Result<Signature, String> {
+    let bytes: [u8; Signature::SIZE] = hex::decode(signature)
+        .map_err(|e| format!("signature: {e}"))?
+        .try_into()
+        .map_err(|_| String::from("signature: expected 48 bytes"))?;
+
+    Signature::from_bytes(&bytes).map_err(|e| format!("signature: {e:?}"))
+}
+
+/// Errors of the offline signing workflow
+#[derive(Debug, thiserror::Error)]
+pub enum OfflineError {
+    /// The file could not be read or written
+    #[error("Failed to access the offline file: {0}")]
+    Io(#[from] io::Error),
+    /// The file is not a valid offline file
+    #[error("Malformed offline file: {0}")]
+    Json(#[from] serde_json::Error),
+    /// A batch of the file is inconsistent
+    #[error("batches[{index}]: {reason}")]
+    Malformed { index: usize, reason: String },
+    /// A batch was not signed yet
+    #[error("batches[{0}] is not signed")]
+    Unsigned(usize),
+    /// The file was signed by another key than the provisioner one
+    #[error("The file was signed by {0}, not by the provisioner key")]
+    Signer(String),
+    /// A signature does not match its payload
+    #[error("batches[{index}]: {source}")]
+    Signature { index: usize, source: VerifyError },
+}
@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::models::{Cause, ChangeType, Decimal, Event, Events};
+
+/// Check the cash leg of every rebalance offsets the value of the securities
+/// bought and sold, `size * price`
+///
+/// The tolerance is relative to the value of the securities, sizes are
+/// rounded by the export and the cash leg is never exact. On top of it the
+/// rounding of the cash leg to the cent is accepted.
+pub fn check_rebalances<'a, I>(
+    accounts: I,
+    tolerance: Decimal,
+) -> Result<(), RebalanceError>
+where
+    I: IntoIterator<Item = &'a (String, Events)>,
+{
+    let mut mismatches = vec![];
+
+    for (account, events) in accounts {
+        for (index, event) in events.events.iter().enumerate() {
+            if event.cause != Cause::Rebalance {
+                continue;
+            }
+
+            let Some((cash, securities, balanced)) = sums(event, tolerance)
+            else {
+                return Err(RebalanceError::Overflow {
+                    account: account.clone(),
+                    event: index,
+                });
+            };
+
+            if !balanced {
+                mismatches.push(RebalanceMismatch {
+                    account: account.clone(),
+                    event: index,
+                    occurrence: event.occurrence,
+                    cash,
+                    securities,
+                });
+            }
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(RebalanceError::Unbalanced(mismatches))
+    }
+}
+
+// sums of the cash and security changes of a rebalance and whether they
+// offset, None if they don't fit a decimal
+fn sums(event: &Event, tolerance: Decimal) -> Option<(Decimal, Decimal, bool)> {
+    let cent = Decimal::new(1, 2);
+    let mut cash = Decimal::ZERO;
+    let mut securities = Decimal::ZERO;
+
+    for change in &event.changes {
+        match change.change_type {
+            ChangeType::Cash => cash = cash.checked_add(change.size)?,
+            ChangeType::Security => {
+                let value = change.size.checked_mul(change.price)?;
+                securities = securities.checked_add(value)?;
+            }
+            ChangeType::Reservation => (),
+        }
+    }
+
+    let accepted =
+        tolerance.checked_mul(securities.abs())?.checked_add(cent)?;
+    let balanced = cash.checked_add(securities)?.abs() <= accepted;
+
+    Some((cash, securities, balanced))
+}
+
+/// A rebalance whose cash leg does not offset its securities
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "{account}: events[{event}]: cash leg {cash} does not offset securities \
+     worth {securities}"
+)]
+pub struct RebalanceMismatch {
+    pub account: String,
+    /// Index of the event in the events of the account
+    pub event: usize,
+    pub occurrence: u64,
+    /// Sum of the cash changes
+    pub cash: Decimal,
+    /// Sum of `size * price` of the security changes
+    pub securities: Decimal,
+}
+
+/// Rebalances that can't be checked or do not offset their cash and
+/// securities
+#[derive(Debug, thiserror::Error)]
+pub enum RebalanceError {
+    /// Some rebalances do not offset their cash and securities
+    Unbalanced(Vec<RebalanceMismatch>),
+    /// The sums of a rebalance don't fit a decimal
+    Overflow { account: String, event: usize },
+}
+
+impl Display for RebalanceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RebalanceError::Unbalanced(mismatches) => {
+                write!(f, "{} unbalanced rebalance(s):", mismatches.len())?;
+                for mismatch in mismatches {
+                    write!(f, "\n  {mismatch}")?;
+                }
+                Ok(())
+            }
+            RebalanceError::Overflow { account, event } => {
+                write!(f, "{account}: events[{event}]: amounts overflow")
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fmt::{self, Display, Formatter};
+
+use dusk_bytes::Serializable;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Batch, BatchKind, Decimal};
+
+/// Outcome of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptStatus {
+    /// The transaction was included in a block
+    Confirmed,
+    /// The batch was confirmed by an earlier run, as recorded in the journal
+    Skipped,
+    /// The batch could not be sent or confirmed, the run stopped
+    Failed,
+}
+
+/// Record of a batch handled by a run, flat so it can be written as a CSV
+/// row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub security: String,
+    pub kind: BatchKind,
+    /// Number of transfers in the batch
+    pub count: usize,
+    /// Sum of the amounts of the transfers
+    pub total: Decimal,
+    /// Hex encoded seed of the payload
+    pub seed: String,
+    pub tx_hash: Option<String>,
+    pub status: ReceiptStatus,
+    pub block_height: Option<u64>,
+    /// RFC 3339 time the transaction was sent at
+    pub submitted_at: Option<String>,
+    /// Milliseconds from sending to confirmation
+    pub confirmation_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl Receipt {
+    /// Receipt of the batch, not sent yet
+    pub(crate) fn new(batch: &Batch, status: ReceiptStatus) -> Self {
+        Self {
+            security: batch.security.to_string(),
+            kind: batch.kind,
+            count: batch.transfers.len(),
+            total: batch.total(),
+            seed: hex::encode(batch.seed().to_bytes()),
+            tx_hash: None,
+            status,
+            block_height: None,
+            submitted_at: None,
+            confirmation_ms: None,
+            error: None,
+        }
+    }
+}
+
+/// Receipts of the batches handled before a run stopped, attached as context
+/// to its error. The last receipt is the failed batch.
+#[derive(Debug, Clone)]
+pub struct Receipts(pub Vec<Receipt>);
+
+impl Display for Receipts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Sending stopped at batch {}", self.0.len())
+    }
+}
@@ -0,0 +1,159 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use dusk_abi::ContractId;
+use serde::{Deserialize, Serialize};
+
+use crate::models::SecurityDefinition;
+
+/// Contract of a security, either a number stored in the last 8 bytes of the
+/// id or the hex encoded 32 bytes of the id
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ContractRef {
+    Id(u64),
+    Hex(String),
+}
+
+impl ContractRef {
+    fn contract_id(&self) -> Result<ContractId, String> {
+        let mut data = [0u8; 32];
+
+        match self {
+            ContractRef::Id(id) => {
+                data[24..].copy_from_slice(&id.to_be_bytes())
+            }
+            ContractRef::Hex(id) => {
+                let bytes = hex::decode(id).map_err(|e| e.to_string())?;
+                if bytes.len() != data.len() {
+                    return Err(format!(
+                        "expected 32 bytes, got {}",
+                        bytes.len()
+                    ));
+                }
+                data.copy_from_slice(&bytes);
+            }
+        }
+
+        Ok(ContractId::from(data))
+    }
+}
+
+/// Mapping of the listed securities to their contracts, by ticker
+///
+/// `Cash` is the contract receiving the cash changes
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "BTreeMap<String, ContractRef>")]
+#[serde(into = "BTreeMap<String, ContractRef>")]
+pub struct Registry {
+    securities: BTreeMap<String, ContractRef>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let securities = [
+            ("Cash", 0x1000),
+            ("TSWE", 0x1001),
+            ("TRET", 0x1002),
+            ("TGBT", 0x1003),
+            ("TCBT", 0x1004),
+        ]
+        .into_iter()
+        .map(|(ticker, id)| (ticker.to_string(), ContractRef::Id(id)))
+        .collect();
+
+        Self { securities }
+    }
+}
+
+impl Registry {
+    /// Load a registry file holding a `[securities]` table
+    pub fn load_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        #[derive(Deserialize)]
+        struct RegistryFile {
+            securities: Registry,
+        }
+
+        let content = fs::read_to_string(path)?;
+        let file: RegistryFile = toml::from_str(&content)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+        Ok(file.securities)
+    }
+
+    /// Register the contract of a security
+    pub fn insert<T: Into<String>>(
+        &mut self,
+        ticker: T,
+        contract: ContractRef,
+    ) -> Result<(), RegistryError> {
+        let ticker = ticker.into();
+        contract
+            .contract_id()
+            .map_err(|e| RegistryError::InvalidContract(ticker.clone(), e))?;
+        self.securities.insert(ticker, contract);
+
+        Ok(())
+    }
+
+    /// Whether the security is listed
+    pub fn contains(&self, security: &SecurityDefinition) -> bool {
+        self.securities.contains_key(&security.to_string())
+    }
+
+    /// Contract of the security
+    pub fn contract_id(
+        &self,
+        security: &SecurityDefinition,
+    ) -> Result<ContractId, RegistryError> {
+        let ticker = security.to_string();
+
+        self.securities
+            .get(&ticker)
+            .ok_or_else(|| RegistryError::UnknownSecurity(ticker.clone()))?
+            .contract_id()
+            .map_err(|e| RegistryError::InvalidContract(ticker, e))
+    }
+}
+
+impl TryFrom<BTreeMap<String, ContractRef>> for Registry {
+    type Error = RegistryError;
+
+    fn try_from(
+        securities: BTreeMap<String, ContractRef>,
+    ) -> Result<Self, Self::Error> {
+        let mut registry = Registry {
+            securities: BTreeMap::new(),
+        };
+        for (ticker, contract) in securities {
+            registry.insert(ticker, contract)?;
+        }
+
+        Ok(registry)
+    }
+}
+
+impl From<Registry> for BTreeMap<String, ContractRef> {
+    fn from(registry: Registry) -> Self {
+        registry.securities
+    }
+}
+
+/// Errors of the security registry
+#[derive(Debug, thiserror::Error)]
+pub enum RegistryError {
+    /// The security is not in the registry
+    #[error("Unknown security {0}, it is not in the registry")]
+    UnknownSecurity(String),
+    /// The contract id of a security is malformed
+    #[error("Invalid contract id for {0}: {1}")]
+    InvalidContract(String, String),
+}
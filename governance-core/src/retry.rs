@@ -0,0 +1,221 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dusk_abi::ContractId;
+use dusk_bls12_381::BlsScalar;
+use rand::Rng;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::warn;
+
+use crate::prelude::*;
+
+/// Kinds of errors that may be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorClass {
+    /// The GraphQL endpoint could not be reached or failed the query
+    Connection,
+    /// The GraphQL endpoint replied without a transaction status
+    Response,
+    /// The node could not be reached to execute the payload
+    Broadcast,
+}
+
+impl ErrorClass {
+    /// Class of a GraphQL error, `None` if it is final
+    pub fn of(err: &GraphQLError) -> Option<Self> {
+        match err {
+            GraphQLError::Generic(_) => Some(Self::Connection),
+            GraphQLError::TxStatus => Some(Self::Response),
+            GraphQLError::Transaction(_) | GraphQLError::Timeout(..) => None,
+        }
+    }
+}
+
+/// How many times and how often to retry transient errors
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts of every call, the first one included
+    pub max_attempts: u32,
+    /// Milliseconds before the first retry, doubled at every attempt
+    pub base_delay: u64,
+    /// Longest delay in milliseconds between two attempts
+    pub max_delay: u64,
+    /// Fraction of the delay randomly added to it, from 0 to 1
+    #[serde(deserialize_with = "fraction")]
+    pub jitter: f64,
+    /// Errors worth retrying
+    pub retryable: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: 500,
+            max_delay: 30_000,
+            jitter: 0.2,
+            retryable: vec![
+                ErrorClass::Connection,
+                ErrorClass::Response,
+                ErrorClass::Broadcast,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry, starting from 1
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        let jitter = match self.jitter > 0.0 {
+            true => rand::thread_rng().gen_range(0.0..self.jitter.min(1.0)),
+            false => 0.0,
+        };
+
+        Duration::from_millis(delay)
+            + Duration::from_millis(delay).mul_f64(jitter)
+    }
+
+    /// Run the call until it succeeds, fails with an error that is not
+    /// retryable or runs out of attempts
+    pub async fn run<T, E, F, Fut, C>(
+        &self,
+        what: &str,
+        metrics: &Metrics,
+        class: C,
+        mut call: F,
+    ) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        C: Fn(&E) -> Option<ErrorClass>,
+        E: Display,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let err = match call().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+
+            let class = match class(&err) {
+                Some(class) if self.retryable.contains(&class) => class,
+                Some(class) => {
+                    warn!("{what} failed: {err}, {class:?} is not retryable");
+                    return Err(err);
+                }
+                None => return Err(err),
+            };
+            if attempt >= self.max_attempts {
+                warn!("{what} failed after {attempt} attempt(s): {err}");
+                return Err(err);
+            }
+
+            let delay = self.delay(attempt);
+            warn!(
+                "{what} failed (attempt {attempt}/{}): {err}, retrying in {}ms",
+                self.max_attempts,
+                delay.as_millis()
+            );
+            metrics.retried(class);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+// a fraction from 0 to 1, larger ones would overflow the delays
+fn fraction<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = f64::deserialize(deserializer)?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(D::Error::custom(format!("{value} is not between 0 and 1")))
+    }
+}
+
+/// Transport retrying the calls of another one under a policy
+pub struct Retry<'a, T: ?Sized> {
+    inner: &'a T,
+    policy: &'a RetryPolicy,
+    metrics: &'a Metrics,
+}
+
+impl<'a, T: ?Sized> Retry<'a, T> {
+    pub fn new(
+        inner: &'a T,
+        policy: &'a RetryPolicy,
+        metrics: &'a Metrics,
+    ) -> Self {
+        Self {
+            inner,
+            policy,
+            metrics,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<B> Broadcaster for Retry<'_, B>
+where
+    B: Broadcaster + ?Sized,
+{
+    async fn execute(
+        &self,
+        contract_id: ContractId,
+        payload: SignedPayload,
+    ) -> anyhow::Result<BlsScalar> {
+        let call = || self.inner.execute(contract_id, payload.clone());
+        // only a payload that never reached the node is executed again
+        let class = |err: &anyhow::Error| match err.downcast_ref() {
+            Some(BroadcastError::Unreachable(_)) => Some(ErrorClass::Broadcast),
+            _ => None,
+        };
+
+        match self
+            .policy
+            .run("Broadcast", self.metrics, class, call)
+            .await
+        {
+            // the status of the transaction tells whether it was included
+            Err(err) => match err.downcast_ref() {
+                Some(BroadcastError::NoReply(tx_hash)) => {
+                    warn!("{err}, waiting for its confirmation");
+                    Ok(*tx_hash)
+                }
+                _ => Err(err),
+            },
+            res => res,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<P> StatusProvider for Retry<'_, P>
+where
+    P: StatusProvider + ?Sized,
+{
+    async fn tx_status(&self, tx_id: &str) -> Result<TxStatus, GraphQLError> {
+        let what = format!("Status query of {tx_id}");
+        let call = || self.inner.tx_status(tx_id);
+        self.policy
+            .run(&what, self.metrics, ErrorClass::of, call)
+            .await
+    }
+}
@@ -0,0 +1,263 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use canonical::EncodeToVec;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::journal::key;
+use crate::models::{Batch, Transfer};
+
+/// What to do with a batch confirmed by an earlier run
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Stop before anything is sent
+    #[default]
+    Refuse,
+    /// Leave the batch out of the run, if all its transfers were confirmed
+    Skip,
+    /// Send the batch again
+    Resend,
+}
+
+/// Fingerprint of a confirmed batch, its op code, seed and transfers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub op: u8,
+    /// Hex encoded seed of the payload
+    pub seed: String,
+    /// See [`Batch::repeat`]
+    #[serde(default)]
+    pub repeat: usize,
+    /// Hex encoded BLAKE3 hashes of the canonical encoding of the transfers,
+    /// so they are recognized when the input is split in other batches
+    #[serde(default)]
+    pub transfers: Vec<String>,
+    /// Same hashes with the timestamps left out, so transfers are
+    /// recognized when the input is sent again with other timestamps
+    #[serde(default)]
+    pub contents: Vec<String>,
+    pub tx_hash: String,
+    /// RFC 3339 time the batch was confirmed at
+    pub confirmed_at: String,
+}
+
+/// Fingerprints of every batch confirmed, by hex encoded contract id
+///
+/// Unlike the journal it is never removed, so the same input cannot be
+/// booked twice
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SentRegistry {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    contracts: BTreeMap<String, Vec<Fingerprint>>,
+    /// Hash of the last audit log entry of the runs, so a truncated log
+    /// is noticed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    audit_head: Option<String>,
+}
+
+impl SentRegistry {
+    /// Open the registry stored at `path`, a missing file is an empty
+    /// registry
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+
+        let mut registry = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e),
+        };
+        registry.path = Some(path);
+
+        Ok(registry)
+    }
+
+    /// Fingerprint of a confirmed batch sharing the seed or a transfer of
+    /// the batch, if any
+    pub fn get(&self, batch: &Batch) -> Option<&Fingerprint> {
+        let (contract, op, seed) = key(batch);
+        let transfers = transfer_hashes(batch);
+
+        self.contracts.get(&contract)?.iter().find(|sent| {
+            sent.op == op
+                && ((sent.seed == seed && sent.repeat == batch.repeat)
+                    || sent.transfers.iter().any(|t| transfers.contains(t)))
+        })
+    }
+
+    /// Whether every transfer of the batch was confirmed, in one or more
+    /// batches. A transfer repeated in the batch must have been confirmed
+    /// as many times.
+    pub fn covers(&self, batch: &Batch) -> bool {
+        let (contract, op, seed) = key(batch);
+        let sent: Vec<_> = match self.contracts.get(&contract) {
+            Some(sent) => sent.iter().filter(|sent| sent.op == op).collect(),
+            None => return false,
+        };
+        if sent
+            .iter()
+            .any(|sent| sent.seed == seed && sent.repeat == batch.repeat)
+        {
+            return true;
+        }
+
+        let mut missing: HashMap<String, usize> = HashMap::new();
+        for transfer in transfer_hashes(batch) {
+            *missing.entry(transfer).or_default() += 1;
+        }
+        for transfer in sent.iter().flat_map(|sent| &sent.transfers) {
+            if let Some(count) = missing.get_mut(transfer) {
+                *count = count.saturating_sub(1);
+            }
+        }
+
+        missing.values().all(|count| *count == 0)
+    }
+
+    /// Fingerprint of a confirmed batch sharing a transfer of the batch,
+    /// whatever their timestamps, if any
+    pub fn resembles(&self, batch: &Batch) -> Option<&Fingerprint> {
+        let (contract, op, _) = key(batch);
+        let contents = content_hashes(batch);
+
+        self.contracts.get(&contract)?.iter().find(|sent| {
+            sent.op == op && sent.contents.iter().any(|t| contents.contains(t))
+        })
+    }
+
+    /// Whether no batch was confirmed yet
+    pub fn is_empty(&self) -> bool {
+        self.contracts.values().all(Vec::is_empty)
+    }
+
+    /// Record the batch as confirmed in the transaction `tx_hash`
+    pub fn confirmed(
+        &mut self,
+        batch: &Batch,
+        tx_hash: &str,
+    ) -> io::Result<()> {
+        let (contract, op, seed) = key(batch);
+
+        self.contracts
+            .entry(contract)
+            .or_default()
+            .push(Fingerprint {
+                op,
+                seed,
+                repeat: batch.repeat,
+                transfers: transfer_hashes(batch),
+                contents: content_hashes(batch),
+                tx_hash: tx_hash.to_string(),
+                confirmed_at: Utc::now().to_rfc3339(),
+            });
+
+        self.save()
+    }
+
+    /// Hash of the last audit log entry recorded
+    pub fn audit_head(&self) -> Option<&str> {
+        self.audit_head.as_deref()
+    }
+
+    /// Record the hash of the last audit log entry
+    pub fn audited(&mut self, head: &str) -> io::Result<()> {
+        self.audit_head = Some(head.to_string());
+        self.save()
+    }
+
+    // write to a temporary file first, so a crash never leaves the registry
+    // half written
+    fn save(&self) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+            fs::rename(tmp, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+// hashes of the transfers of the batch, see `Fingerprint::transfers`
+fn transfer_hashes(batch: &Batch) -> Vec<String> {
+    batch.transfers.iter().map(hash).collect()
+}
+
+// hashes of the transfers of the batch without their timestamps, see
+// `Fingerprint::contents`
+fn content_hashes(batch: &Batch) -> Vec<String> {
+    batch
+        .transfers
+        .iter()
+        .map(|tx| {
+            hash(&Transfer {
+                timestamp: 0,
+                ..*tx
+            })
+        })
+        .collect()
+}
+
+fn hash(tx: &Transfer) -> String {
+    blake3::hash(&tx.encode_to_vec()).to_hex().to_string()
+}
+
+/// A batch of the run that was confirmed, in part or in full, by an earlier
+/// one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub security: String,
+    /// Hex encoded seed of the confirmed batch
+    pub seed: String,
+    pub tx_hash: String,
+    pub confirmed_at: String,
+}
+
+impl Duplicate {
+    /// The batch of the run, confirmed as `sent`
+    pub fn new(batch: &Batch, sent: &Fingerprint) -> Self {
+        Self {
+            security: batch.security.to_string(),
+            seed: sent.seed.clone(),
+            tx_hash: sent.tx_hash.clone(),
+            confirmed_at: sent.confirmed_at.clone(),
+        }
+    }
+}
+
+impl Display for Duplicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} batch {} was confirmed in {} at {}",
+            self.security, self.seed, self.tx_hash, self.confirmed_at
+        )
+    }
+}
+
+/// The run holds batches already confirmed, refused by the duplicate policy
+#[derive(Debug, thiserror::Error)]
+pub struct DuplicateError(pub Vec<Duplicate>);
+
+impl Display for DuplicateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} batch(es) already sent:", self.0.len())?;
+        for duplicate in &self.0 {
+            write!(f, "\n  {duplicate}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use dusk_abi::ContractId;
+use dusk_bls12_381::BlsScalar;
+use dusk_bls12_381_sign::Signature;
+use dusk_wallet::{gas::Gas, Wallet};
+
+use crate::prelude::*;
+
+/// Payload signed with the provisioner key, as received by the contract
+pub type SignedPayload = (Signature, u32, Payload);
+
+/// Broadcasts signed payloads to the governance contracts
+#[async_trait(?Send)]
+pub trait Broadcaster {
+    /// Execute the payload on the contract, returning the transaction hash
+    async fn execute(
+        &self,
+        contract_id: ContractId,
+        payload: SignedPayload,
+    ) -> anyhow::Result<BlsScalar>;
+}
+
+/// Broadcast errors telling whether the payload may have been sent
+#[derive(Debug, thiserror::Error)]
+pub enum BroadcastError {
+    /// The node could not be reached, the payload was not sent
+    #[error("The node is unreachable: {0}")]
+    Unreachable(String),
+    /// The payload was sent in the transaction but the node did not
+    /// acknowledge it
+    #[error("No reply from the node after sending {0:x}")]
+    NoReply(BlsScalar),
+}
+
+/// Looks up the status of the transactions sent
+#[async_trait(?Send)]
+pub trait StatusProvider {
+    /// Obtain transaction status
+    async fn tx_status(&self, tx_id: &str) -> Result<TxStatus, GraphQLError>;
+}
+
+/// Wait for a transaction to be confirmed (included in a block), querying
+/// its status every `interval`. Returns the block height if known.
+pub async fn wait_for<P, S>(
+    provider: &P,
+    tx_id: &str,
+    timeout: Duration,
+    interval: Duration,
+    status: S,
+) -> Result<Option<u64>, GraphQLError>
+where
+    P: StatusProvider + ?Sized,
+    S: Fn(&str),
+{
+    let start = Instant::now();
+
+    loop {
+        match provider.tx_status(tx_id).await? {
+            TxStatus::Ok { block_height } => return Ok(block_height),
+            TxStatus::Error(err) => return Err(GraphQLError::Transaction(err)),
+            TxStatus::NotFound => (),
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(GraphQLError::Timeout(
+                tx_id.to_string(),
+                timeout.as_secs(),
+            ));
+        }
+
+        status(
+            format!(
+                "Waiting for confirmation... ({}/{}s)",
+                elapsed.as_secs(),
+                timeout.as_secs()
+            )
+            .as_str(),
+        );
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Broadcasts through a wallet connected to a Rusk node
+pub struct WalletBroadcaster {
+    wallet: Wallet<SecureWallet>,
+    gas_limit: u64,
+    gas_price: Option<u64>,
+}
+
+impl WalletBroadcaster {
+    pub fn new(
+        wallet: Wallet<SecureWallet>,
+        gas_limit: u64,
+        gas_price: Option<u64>,
+    ) -> Self {
+        Self {
+            wallet,
+            gas_limit,
+            gas_price,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Broadcaster for WalletBroadcaster {
+    async fn execute(
+        &self,
+        contract_id: ContractId,
+        payload: SignedPayload,
+    ) -> anyhow::Result<BlsScalar> {
+        // TODO: Make sure this is correct
+        let sender = self.wallet.default_address();
+
+        let mut gas = Gas::new(self.gas_limit);
+        gas.set_price(self.gas_price);
+
+        // finish sending data to blockchain
+        let tx = self
+            .wallet
+            .execute(sender, contract_id, payload, gas)
+            .await
+            .map_err(|err| match err {
+                dusk_wallet::Error::Offline
+                | dusk_wallet::Error::Network(_) => {
+                    BroadcastError::Unreachable(err.to_string()).into()
+                }
+                err => anyhow::Error::from(err),
+            })?;
+
+        Ok(tx.hash())
+    }
+}
+
+/// In-memory node that records the executed payloads and replies with
+/// scripted transaction statuses, to run the submission flow offline
+#[derive(Default)]
+pub struct MockNode {
+    executed: Mutex<Vec<(ContractId, SignedPayload)>>,
+    statuses: Mutex<VecDeque<TxStatus>>,
+    failures: Mutex<(usize, usize)>,
+    lost_replies: Mutex<usize>,
+}
+
+impl MockNode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the statuses returned by the next queries, once they run out
+    /// every transaction is confirmed, at the height of the payloads executed
+    /// so far
+    pub fn script<I>(&self, statuses: I)
+    where
+        I: IntoIterator<Item = TxStatus>,
+    {
+        self.statuses.lock().unwrap().extend(statuses);
+    }
+
+    /// Fail the next executions and status queries, as a node restarting
+    /// would
+    pub fn fail(&self, executions: usize, queries: usize) {
+        *self.failures.lock().unwrap() = (executions, queries);
+    }
+
+    /// Execute the next payloads without acknowledging them, as a node
+    /// dropping the connection would
+    pub fn lose_replies(&self, executions: usize) {
+        *self.lost_replies.lock().unwrap() = executions;
+    }
+
+    /// Payloads executed so far, in order
+    pub fn executed(&self) -> Vec<(ContractId, SignedPayload)> {
+        self.executed.lock().unwrap().clone()
+    }
+}
+
+#[async_trait(?Send)]
+impl Broadcaster for MockNode {
+    async fn execute(
+        &self,
+        contract_id: ContractId,
+        payload: SignedPayload,
+    ) -> anyhow::Result<BlsScalar> {
+        let (executions, _) = &mut *self.failures.lock().unwrap();
+        if *executions > 0 {
+            *executions -= 1;
+            let err = BroadcastError::Unreachable("Connection refused".into());
+            return Err(err.into());
+        }
+
+        let mut executed = self.executed.lock().unwrap();
+        executed.push((contract_id, payload));
+        let tx_hash = BlsScalar::from(executed.len() as u64);
+
+        let lost = &mut *self.lost_replies.lock().unwrap();
+        if *lost > 0 {
+            *lost -= 1;
+            return Err(BroadcastError::NoReply(tx_hash).into());
+        }
+
+        Ok(tx_hash)
+    }
+}
+
+#[async_trait(?Send)]
+impl StatusProvider for MockNode {
+    async fn tx_status(&self, _tx_id: &str) -> Result<TxStatus, GraphQLError> {
+        let (_, queries) = &mut *self.failures.lock().unwrap();
+        if *queries > 0 {
+            *queries -= 1;
+            return Err(GraphQLError::TxStatus);
+        }
+
+        let status = self.statuses.lock().unwrap().pop_front();
+        // every executed payload is a block
+        let block_height = self.executed.lock().unwrap().len() as u64;
+        Ok(status.unwrap_or(TxStatus::Ok {
+            block_height: Some(block_height),
+        }))
+    }
+}
@@ -0,0 +1,87 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use canonical::{Canon, Source};
+use dusk_bls12_381::BlsScalar;
+use dusk_bls12_381_sign::PublicKey as BlsPublicKey;
+
+use crate::models::{BatchKind, Transfer};
+use crate::transport::SignedPayload;
+use crate::{seed, signed_buffer};
+
+/// Content of a signed payload whose signature and seed were checked
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedPayload {
+    pub seed: BlsScalar,
+    pub op: u8,
+    /// Kind of the transfers, if the operation code is known
+    pub kind: Option<BatchKind>,
+    pub transfers: Vec<Transfer>,
+}
+
+/// Decode a canonical encoded signed payload, as sent to the contract, and
+/// check it was signed by the provisioner key
+///
+/// The signature covers the length prefixed payload, the seed must be the
+/// one of the transfers and nothing may follow the payload
+pub fn verify_payload(
+    bytes: &[u8],
+    pk: &BlsPublicKey,
+) -> Result<VerifiedPayload, VerifyError> {
+    let mut source = Source::new(bytes);
+    let signed = SignedPayload::decode(&mut source)
+        .map_err(|e| VerifyError::Decode(format!("{e:?}")))?;
+
+    let trailing = bytes.len() - signed.encoded_len();
+    if trailing > 0 {
+        return Err(VerifyError::Trailing(trailing));
+    }
+
+    let (signature, len, payload) = signed;
+
+    let buffer = signed_buffer(&payload);
+    if buffer.len() != len as usize {
+        return Err(VerifyError::Length {
+            expected: buffer.len(),
+            found: len,
+        });
+    }
+
+    pk.verify(&signature, &buffer)
+        .map_err(|_| VerifyError::Signature)?;
+
+    let (seed_found, op, transfers) = payload;
+    if seed(&transfers) != seed_found {
+        return Err(VerifyError::Seed);
+    }
+
+    Ok(VerifiedPayload {
+        seed: seed_found,
+        op,
+        kind: BatchKind::from_op(op),
+        transfers,
+    })
+}
+
+/// Reasons a signed payload does not verify
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    /// The bytes are not a canonical encoded signed payload
+    #[error("Malformed signed payload: {0}")]
+    Decode(String),
+    /// Bytes are left after the signed payload
+    #[error("{0} byte(s) after the signed payload")]
+    Trailing(usize),
+    /// The length prefix does not match the payload
+    #[error("Length prefix is {found}, the payload needs {expected}")]
+    Length { expected: usize, found: u32 },
+    /// The signature was not made by the given key over the payload
+    #[error("The signature does not match the public key")]
+    Signature,
+    /// The seed is not the one of the transfers
+    #[error("The seed does not match the transfers")]
+    Seed,
+}
@@ -0,0 +1,45 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use governance_core::prelude::*;
+
+const JSON: &str = r#"{"Dusk1": {"events": [
+    {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 1000.0,
+         "securityDefinition": "None", "price": 1}]},
+    {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": -750.5,
+         "securityDefinition": "None", "price": 1},
+        {"accountExternalId": "Dusk1", "type": "Security", "size": 30.0,
+         "securityDefinition": "TSWE", "price": 25.0}]},
+    {"occurrence": "2022-09-27T12:00:00Z", "cause": "Fee", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 2.25,
+         "securityDefinition": "None", "price": 1}]}
+]}}"#;
+
+const CSV: &str = "\
+account,occurrence,cause,type,security,size,price
+Dusk1,2022-09-25T10:00:00Z,Deposit,Cash,None,1000.0,1
+Dusk1,2022-09-26T12:00:00Z,Rebalance,Cash,None,-750.5,1
+Dusk1,2022-09-26T12:00:00Z,Rebalance,Security,TSWE,30.0,25.0
+Dusk1,2022-09-27T12:00:00Z,Fee,Cash,None,2.25,1
+";
+
+// keys derived from the account names, the inputs have no key registry
+fn options() -> ParseOptions {
+    ParseOptions {
+        derive_keys: true,
+        ..ParseOptions::default()
+    }
+}
+
+#[test]
+fn csv_matches_json() {
+    let json = json_bytes(JSON, &options()).expect("valid json");
+    let csv = csv_bytes(CSV, &options()).expect("valid csv");
+
+    assert_eq!(json.into_transfers(), csv.into_transfers());
+}
@@ -26,6 +26,10 @@ mod test {
         timestamp.0
     }
 
+    fn decimal(number: &str) -> Decimal {
+        Decimal::from_str(number).expect("Cannot convert number to decimal")
+    }
+
     #[test]
     fn json_from_file() {
         let bytes = include_bytes!("data.json");
@@ -56,9 +60,9 @@ mod test {
                                             "TestAccount1"
                                         ),
                                         change_type: ChangeType::Cash,
-                                        size: 100000.0,
+                                        size: decimal("100000.0"),
                                         security: SecurityDefinition::None,
-                                        price: 1.0,
+                                        price: decimal("1.0"),
                                     },]
                                 },
                                 Event {
@@ -72,45 +76,57 @@ mod test {
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Cash,
-                                            size: -99814.8,
+                                            size: decimal("-99814.8"),
                                             security: SecurityDefinition::None,
-                                            price: 1.0,
+                                            price: decimal("1.0"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 984.0,
-                                            security: SecurityDefinition::Tswe,
-                                            price: 25.36,
+                                            size: decimal("984.0"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TSWE")
+                                                ),
+                                            price: decimal("25.36"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 681.0,
-                                            security: SecurityDefinition::Tret,
-                                            price: 36.65,
+                                            size: decimal("681.0"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TRET")
+                                                ),
+                                            price: decimal("36.65"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 2131.0,
-                                            security: SecurityDefinition::Tgbt,
-                                            price: 11.71,
+                                            size: decimal("2131.0"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TGBT")
+                                                ),
+                                            price: decimal("11.71"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 1585.0,
-                                            security: SecurityDefinition::Tcbt,
-                                            price: 15.74,
+                                            size: decimal("1585.0"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TCBT")
+                                                ),
+                                            price: decimal("15.74"),
                                         }
                                     ]
                                 },
@@ -124,9 +140,9 @@ mod test {
                                             "TestAccount1"
                                         ),
                                         change_type: ChangeType::Cash,
-                                        size: 3000.0,
+                                        size: decimal("3000.0"),
                                         security: SecurityDefinition::None,
-                                        price: 1.0,
+                                        price: decimal("1.0"),
                                     },]
                                 },
                                 Event {
@@ -140,45 +156,57 @@ mod test {
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: -10.9919,
-                                            security: SecurityDefinition::Tswe,
-                                            price: 27.3,
+                                            size: decimal("-10.9919"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TSWE")
+                                                ),
+                                            price: decimal("27.3"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 30.3859,
-                                            security: SecurityDefinition::Tret,
-                                            price: 37.35,
+                                            size: decimal("30.3859"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TRET")
+                                                ),
+                                            price: decimal("37.35"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 57.1205,
-                                            security: SecurityDefinition::Tcbt,
-                                            price: 16.18,
+                                            size: decimal("57.1205"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TCBT")
+                                                ),
+                                            price: decimal("16.18"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Security,
-                                            size: 95.2472,
-                                            security: SecurityDefinition::Tgbt,
-                                            price: 11.94,
+                                            size: decimal("95.2472"),
+                                            security:
+                                                SecurityDefinition::Ticker(
+                                                    String::from("TGBT")
+                                                ),
+                                            price: decimal("11.94"),
                                         },
                                         Change {
                                             account_external_id: String::from(
                                                 "TestAccount1"
                                             ),
                                             change_type: ChangeType::Cash,
-                                            size: -2896.09,
+                                            size: decimal("-2896.09"),
                                             security: SecurityDefinition::None,
-                                            price: 1.0,
+                                            price: decimal("1.0"),
                                         },
                                     ]
                                 }
@@ -0,0 +1,187 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::str::FromStr;
+
+use governance_core::prelude::*;
+
+const JSON: &str = r#"{
+"Dusk1": {"events": [
+    {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 100,
+         "securityDefinition": "None", "price": 1}]},
+    {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Security", "size": 3.5,
+         "securityDefinition": "TSWE", "price": 25},
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": -87.5,
+         "securityDefinition": "None", "price": 1}]},
+    {"occurrence": "2022-09-27T12:00:00Z", "cause": "Withdrawal", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": -20,
+         "securityDefinition": "None", "price": 1}]}
+]},
+"Dusk2": {"events": [
+    {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+        {"accountExternalId": "Dusk2", "type": "Security", "size": -1,
+         "securityDefinition": "TRET", "price": 30},
+        {"accountExternalId": "Dusk2", "type": "Cash", "size": 30,
+         "securityDefinition": "None", "price": 1}]}
+]}}"#;
+
+fn decimal(number: &str) -> Decimal {
+    Decimal::from_str(number).expect("Cannot convert number to decimal")
+}
+
+#[test]
+fn balances_and_issues() {
+    let accounts = json_events(JSON).expect("valid json");
+    let ledger = Ledger::from_events(&accounts);
+
+    let tswe = SecurityDefinition::Ticker(String::from("TSWE"));
+    let tret = SecurityDefinition::Ticker(String::from("TRET"));
+    assert_eq!(ledger.balance("Dusk1", &tswe), decimal("3.5"));
+    assert_eq!(
+        ledger.balance("Dusk1", &SecurityDefinition::Cash),
+        decimal("-7.5")
+    );
+    assert_eq!(ledger.balance("Dusk2", &tret), decimal("-1"));
+
+    let history = &ledger.account("Dusk1").expect("known account").history;
+    assert_eq!(history.len(), 4);
+
+    let issues: Vec<_> = ledger
+        .issues()
+        .iter()
+        .map(|issue| (issue.account.as_str(), issue.event, issue.kind.clone()))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            (
+                "Dusk1",
+                2,
+                IssueKind::OverWithdrawal {
+                    amount: decimal("20"),
+                    held: decimal("12.5")
+                }
+            ),
+            ("Dusk2", 0, IssueKind::NegativeBalance(decimal("-1"))),
+        ]
+    );
+    assert!(ledger.check().is_err());
+}
+
+#[test]
+fn unbalanced_rebalances_are_reported() {
+    // the cash legs of two rebalances of the sample export are swapped
+    let accounts =
+        json_events(include_bytes!("data.json")).expect("valid json");
+    match check_rebalances(&accounts, decimal("0.001")) {
+        Err(RebalanceError::Unbalanced(mismatches)) => {
+            let found: Vec<_> = mismatches
+                .iter()
+                .map(|m| (m.account.as_str(), m.event))
+                .collect();
+            assert_eq!(found, [("Dusk2", 2), ("Dusk4", 1)]);
+        }
+        res => panic!("expected mismatches, got {res:?}"),
+    }
+
+    let json = JSON.replace(r#""size": -87.5"#, r#""size": -78.5"#);
+    let accounts = json_events(json).expect("valid json");
+    match check_rebalances(&accounts, decimal("0.001")) {
+        Err(RebalanceError::Unbalanced(mismatches)) => {
+            assert_eq!(mismatches.len(), 1);
+            assert_eq!(mismatches[0].account, "Dusk1");
+            assert_eq!(mismatches[0].event, 1);
+            assert_eq!(mismatches[0].cash, decimal("-78.5"));
+            assert_eq!(mismatches[0].securities, decimal("87.5"));
+        }
+        res => panic!("expected a mismatch, got {res:?}"),
+    }
+}
+
+#[test]
+fn reservations_are_held_until_settled() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-12-05T12:00:00Z", "cause": "Deposit", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash", "size": 100,
+             "securityDefinition": "None", "price": 1}]},
+        {"occurrence": "2022-12-05T13:00:00Z", "cause": "Withdrawal", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Reservation", "size": -10,
+             "securityDefinition": "None", "price": 1}]}
+    ]}}"#;
+    let settlement = r#",
+        {"occurrence": "2022-12-05T13:01:00Z", "cause": "Withdrawal", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash", "size": -10,
+             "securityDefinition": "None", "price": 1}]}
+    ]}}"#;
+
+    let accounts = json_events(json).expect("valid json");
+    let ledger = Ledger::from_events(&accounts);
+    let account = ledger.account("Dusk1").expect("known account");
+    assert_eq!(account.reserved[&SecurityDefinition::Cash], decimal("10"));
+    assert_eq!(account.balances[&SecurityDefinition::Cash], decimal("100"));
+
+    let settled = json.replacen("\n    ]}}", settlement, 1);
+    let accounts = json_events(settled).expect("valid json");
+    let ledger = Ledger::from_events(&accounts);
+    let account = ledger.account("Dusk1").expect("known account");
+    assert_eq!(account.reserved[&SecurityDefinition::Cash], decimal("0"));
+    assert_eq!(account.balances[&SecurityDefinition::Cash], decimal("90"));
+    assert!(ledger.issues().is_empty());
+
+    // reservations stay off chain
+    let options = ParseOptions {
+        derive_keys: true,
+        ..ParseOptions::default()
+    };
+    let batches = json_bytes(json, &options)
+        .expect("valid json")
+        .into_batches(&options.registry, &BatchLimits::default())
+        .expect("registered securities");
+    let kinds: Vec<_> = batches.iter().map(|batch| batch.kind).collect();
+    assert_eq!(kinds, [BatchKind::Transfer]);
+}
+
+#[test]
+fn overflowing_amounts_are_reported() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash",
+             "size": 5e28,
+             "securityDefinition": "None", "price": 1}]},
+        {"occurrence": "2022-09-25T11:00:00Z", "cause": "Deposit", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash",
+             "size": 5e28,
+             "securityDefinition": "None", "price": 1}]},
+        {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Security",
+             "size": 5e28,
+             "securityDefinition": "TSWE", "price": 25}]}
+    ]}}"#;
+
+    let accounts = json_events(json).expect("valid json");
+    let ledger = Ledger::from_events(&accounts);
+    // the holdings are left as they were before the overflow
+    let account = ledger.account("Dusk1").expect("known account");
+    assert_eq!(
+        account.balances[&SecurityDefinition::Cash],
+        account.history[0].balance
+    );
+    assert!(matches!(
+        ledger.issues(),
+        [LedgerIssue {
+            event: 1,
+            kind: IssueKind::Overflow(_),
+            ..
+        }]
+    ));
+
+    assert!(matches!(
+        check_rebalances(&accounts, decimal("0.001")),
+        Err(RebalanceError::Overflow { event: 2, .. })
+    ));
+}
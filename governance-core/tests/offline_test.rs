@@ -0,0 +1,182 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::path::PathBuf;
+
+use canonical::EncodeToVec;
+use dusk_bls12_381_sign::{PublicKey, SecretKey};
+use dusk_wallet::WalletPath;
+use governance_core::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+const JSON: &str = r#"{"Dusk1": {"events": [
+    {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 1000.0,
+         "securityDefinition": "None", "price": 1}]},
+    {"occurrence": "2022-09-27T12:00:00Z", "cause": "Fee", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 2.25,
+         "securityDefinition": "None", "price": 1}]}
+]}}"#;
+
+fn exported() -> OfflineFile {
+    let options = ParseOptions {
+        derive_keys: true,
+        ..ParseOptions::default()
+    };
+    let batches = json_bytes(JSON, &options)
+        .expect("valid json")
+        .into_batches(&options.registry, &BatchLimits::default())
+        .expect("registered securities");
+
+    OfflineFile::from_batches(batches)
+}
+
+// the online host has no provisioner keystore, the wallet doesn't exist
+fn governance() -> Governance {
+    let wallet = SecureWallet {
+        path: WalletPath::from(PathBuf::from("missing/wallet.dat")),
+        pwd: String::new(),
+    };
+    let config = Config {
+        poll_interval: 1,
+        ..Config::default()
+    };
+
+    Governance::from_config(wallet, config)
+}
+
+#[tokio::test]
+async fn signed_file_is_submitted() {
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(0xbeef));
+    let pk = PublicKey::from(&sk);
+    let mut file = exported();
+    assert!(matches!(file.signed(), Err(OfflineError::Unsigned(0))));
+
+    file.sign(&sk).expect("valid payloads");
+
+    // the file survives the trip between the offline and online hosts
+    let json = serde_json::to_string(&file).expect("serializable file");
+    let file: OfflineFile = serde_json::from_str(&json).expect("valid file");
+
+    let node = MockNode::new();
+    governance()
+        .submit_file_with(file.clone(), &pk, &node, &node)
+        .await
+        .expect("all batches confirmed");
+
+    let executed = node.executed();
+    assert_eq!(executed.len(), file.batches.len());
+    for ((_, (_, _, (_, op, transfers))), batch) in
+        executed.iter().zip(&file.batches)
+    {
+        assert_eq!(*op, batch.op);
+        assert_eq!(transfers.len(), batch.count);
+    }
+}
+
+#[test]
+fn tampered_payloads_are_rejected() {
+    let mut file = exported();
+    file.batches[0].count += 1;
+
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(0xbeef));
+    assert!(matches!(
+        file.sign(&sk),
+        Err(OfflineError::Malformed { index: 0, .. })
+    ));
+
+    let mut file = exported();
+    let seed = file.batches[1].seed.clone();
+    file.batches[0].seed = seed;
+    assert!(matches!(
+        file.sign(&sk),
+        Err(OfflineError::Malformed { index: 0, .. })
+    ));
+}
+
+#[test]
+fn signed_payloads_are_verified() {
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(0xbeef));
+    let pk = PublicKey::from(&sk);
+    let mut file = exported();
+    file.sign(&sk).expect("valid payloads");
+
+    let (batch, signed) = file.signed().expect("signed file").remove(0);
+    let bytes = signed.encode_to_vec();
+
+    let verified = verify_payload(&bytes, &pk).expect("valid signature");
+    assert_eq!(verified.seed, batch.seed());
+    assert_eq!(verified.kind, Some(batch.kind));
+    assert_eq!(verified.transfers, batch.transfers);
+
+    let other =
+        PublicKey::from(&SecretKey::random(&mut StdRng::seed_from_u64(1)));
+    assert!(matches!(
+        verify_payload(&bytes, &other),
+        Err(VerifyError::Signature)
+    ));
+
+    // a changed amount breaks the signature
+    let mut tampered = bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert!(verify_payload(&tampered, &pk).is_err());
+
+    // so do bytes smuggled after the payload
+    let mut padded = bytes.clone();
+    padded.extend_from_slice(&[0, 0]);
+    assert!(matches!(
+        verify_payload(&padded, &pk),
+        Err(VerifyError::Trailing(2))
+    ));
+}
+
+#[test]
+fn files_signed_by_another_key_are_refused() {
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(0xbeef));
+    let pk = PublicKey::from(&sk);
+    let other = SecretKey::random(&mut StdRng::seed_from_u64(1));
+
+    let mut file = exported();
+    file.sign(&other).expect("valid payloads");
+    assert!(matches!(file.verified(&pk), Err(OfflineError::Signer(_))));
+
+    // the signer field alone is not trusted
+    file.signer = None;
+    assert!(matches!(
+        file.verified(&pk),
+        Err(OfflineError::Signature { index: 0, .. })
+    ));
+
+    let mut file = exported();
+    file.sign(&sk).expect("valid payloads");
+    let signature = file.batches[0].signature.clone();
+    file.batches[1].signature = signature;
+    assert!(matches!(
+        file.verified(&pk),
+        Err(OfflineError::Signature { index: 1, .. })
+    ));
+}
+
+#[tokio::test]
+async fn files_of_another_provisioner_are_not_submitted() {
+    let sk = SecretKey::random(&mut StdRng::seed_from_u64(0xbeef));
+    let other = SecretKey::random(&mut StdRng::seed_from_u64(1));
+    let mut file = exported();
+    file.sign(&other).expect("valid payloads");
+
+    let node = MockNode::new();
+    let err = governance()
+        .submit_file_with(file, &PublicKey::from(&sk), &node, &node)
+        .await
+        .expect_err("signed by another key");
+
+    assert!(matches!(
+        err.downcast_ref::<OfflineError>(),
+        Some(OfflineError::Signer(_))
+    ));
+    assert!(node.executed().is_empty());
+}
@@ -0,0 +1,283 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::str::FromStr;
+
+use dusk_bytes::Serializable;
+use governance_core::prelude::*;
+
+// keys derived from the account names, the inputs have no key registry
+fn options() -> ParseOptions {
+    ParseOptions {
+        derive_keys: true,
+        ..ParseOptions::default()
+    }
+}
+
+fn decimal(number: &str) -> Decimal {
+    Decimal::from_str(number).expect("Cannot convert number to decimal")
+}
+
+#[test]
+fn exact_amounts() {
+    let tx = Transfer::new(decimal("99814.8"), 0).expect("valid amount");
+    assert_eq!(tx.amount, 428_701_301_556_966);
+
+    // 429_496_729.5 units, truncated toward zero as the contract expects
+    let tx = Transfer::new(decimal("0.1"), 0).expect("valid amount");
+    assert_eq!(tx.amount, 429_496_729);
+    let tx = Transfer::new(decimal("0.999999999"), 0).expect("valid amount");
+    assert_eq!(tx.amount, 4_294_967_290);
+    // displayed with the digits it was parsed with
+    assert_eq!(tx.decimal_amount(), decimal("0.999999999"));
+}
+
+#[test]
+fn invalid_amounts() {
+    assert!(matches!(
+        Transfer::new(decimal("-1"), 0),
+        Err(AmountError::Negative(_))
+    ));
+    assert!(matches!(
+        Transfer::new(decimal("10000000000000000000"), 0),
+        Err(AmountError::OutOfRange(_))
+    ));
+    assert!(matches!(
+        Transfer::new(decimal("0.0000000001"), 0),
+        Err(AmountError::Precision(_))
+    ));
+    assert!(matches!(
+        Transfer::new(decimal("1.0000000001"), 0),
+        Err(AmountError::Precision(_))
+    ));
+}
+
+#[test]
+fn sizes_are_parsed_exactly() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash",
+             "size": 99814.8, "securityDefinition": "None",
+             "price": 1}]}
+    ]}}"#;
+
+    let map = json_bytes(json, &options()).expect("valid json");
+    let transfers = map.into_transfers();
+    let (deposits, _) = &transfers[&SecurityDefinition::Cash];
+
+    assert_eq!(deposits[0].amount, 428_701_301_556_966);
+}
+
+#[test]
+fn outflows_are_sent_as_magnitudes() {
+    // exports sign withdrawals and fees either way, the cause gives the
+    // direction and the contract only stores unsigned amounts
+    let json = |size: &str| {
+        format!(
+            r#"{{"Dusk1": {{"events": [
+            {{"occurrence": "2022-09-27T12:00:00Z", "cause": "Withdrawal",
+             "changes": [{{"accountExternalId": "Dusk1", "type": "Cash",
+             "size": {size}, "securityDefinition": "None", "price": 1}}]}},
+            {{"occurrence": "2022-09-27T12:00:00Z", "cause": "Fee",
+             "changes": [{{"accountExternalId": "Dusk1", "type": "Cash",
+             "size": {size}, "securityDefinition": "None", "price": 1}}]}}
+        ]}}}}"#
+        )
+    };
+    let key = public_key("Dusk1");
+
+    for size in ["-2.25", "2.25"] {
+        let transfers = json_bytes(json(size), &options())
+            .expect("valid json")
+            .into_transfers();
+        let (withdrawals, fees) = &transfers[&SecurityDefinition::Cash];
+
+        for tx in [&withdrawals[0], &fees[0]] {
+            assert_eq!(tx.amount, 9_663_676_413);
+            assert_eq!(tx.from, Some(key));
+            assert_eq!(tx.to, None);
+        }
+    }
+}
+
+#[test]
+fn invalid_changes_are_reported() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash", "size": -5,
+             "securityDefinition": "None", "price": 1}]},
+        {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash", "size": -5,
+             "securityDefinition": "None", "price": 1},
+            {"accountExternalId": "Dusk1", "type": "Security", "size": 1,
+             "securityDefinition": "None", "price": 5}]}
+    ]}}"#;
+
+    match json_bytes(json, &options()) {
+        Err(ParseError::Changes(changes)) => {
+            let found: Vec<_> = changes
+                .iter()
+                .map(|c| (c.account.as_str(), c.event, c.change))
+                .collect();
+            assert_eq!(found, [("Dusk1", 0, 0), ("Dusk1", 1, 1)]);
+            assert!(matches!(changes[0].reason, ChangeError::Amount(_)));
+            assert!(matches!(changes[1].reason, ChangeError::MissingSecurity));
+        }
+        res => panic!("expected invalid changes, got {res:?}"),
+    }
+}
+
+#[test]
+fn malformed_event_is_located() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit",
+         "changes": []},
+        {"occurrence": "2022-09-25T10:00:00Z", "cause": "Refund",
+         "changes": []}
+    ]}}"#;
+
+    assert!(matches!(
+        json_bytes(json, &options()),
+        Err(ParseError::Event { event: 1, .. })
+    ));
+    assert!(matches!(
+        json_bytes("[]", &options()),
+        Err(ParseError::NotAnObject)
+    ));
+}
+
+#[test]
+fn tickers_are_checked_against_the_registry() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Security", "size": 2,
+             "securityDefinition": "TNEW", "price": 10}]}
+    ]}}"#;
+
+    match json_bytes(json, &options()) {
+        Err(ParseError::Changes(changes)) => assert!(matches!(
+            &changes[0].reason,
+            ChangeError::UnknownSecurity(ticker) if ticker == "TNEW"
+        )),
+        res => panic!("expected an unknown security, got {res:?}"),
+    }
+
+    let mut registry = Registry::default();
+    registry
+        .insert("TNEW", ContractRef::Hex("ab".repeat(32)))
+        .expect("valid contract id");
+    let options = ParseOptions {
+        registry,
+        ..options()
+    };
+
+    let batches = json_bytes(json, &options)
+        .expect("registered ticker")
+        .into_batches(&options.registry, &BatchLimits::default())
+        .expect("registered ticker");
+    assert_eq!(batches[0].contract_id.as_bytes(), [0xab; 32]);
+}
+
+#[test]
+fn accounts_need_a_registered_key() {
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-27T12:00:00Z", "cause": "Withdrawal", "changes": [
+            {"accountExternalId": "EXT-1", "type": "Cash", "size": -5,
+             "securityDefinition": "None", "price": 1}]}
+    ]}}"#;
+
+    match json_bytes(json, &ParseOptions::default()) {
+        Err(ParseError::Changes(changes)) => assert!(matches!(
+            &changes[0].reason,
+            ChangeError::UnmappedAccount(account) if account == "Dusk1"
+        )),
+        res => panic!("expected an unmapped account, got {res:?}"),
+    }
+
+    // the external id of the change is enough to find the key
+    let key = public_key("a key held by the user");
+    let mut keys = KeyRegistry::default();
+    keys.insert("EXT-1", &bs58::encode(key.to_bytes()).into_string())
+        .expect("valid public key");
+    let options = ParseOptions {
+        keys,
+        ..ParseOptions::default()
+    };
+
+    let transfers = json_bytes(json, &options)
+        .expect("registered account")
+        .into_transfers();
+    let (withdrawals, _) = &transfers[&SecurityDefinition::Cash];
+    assert_eq!(withdrawals[0].from, Some(key));
+
+    // deposits are credited to the key of the account too
+    let deposit = json.replace("Withdrawal", "Deposit").replace("-5", "5");
+    assert!(matches!(
+        json_bytes(&deposit, &ParseOptions::default()),
+        Err(ParseError::Changes(_))
+    ));
+    let transfers = json_bytes(&deposit, &options)
+        .expect("registered account")
+        .into_transfers();
+    let (deposits, _) = &transfers[&SecurityDefinition::Cash];
+    assert_eq!(deposits[0].to, Some(key));
+
+    let mut keys = KeyRegistry::default();
+    assert!(matches!(
+        keys.insert("Dusk1", "not a key"),
+        Err(KeyError::InvalidKey(..))
+    ));
+}
+
+#[test]
+fn events_are_selected_and_batched_by_day() {
+    let deposit = |occurrence: &str| {
+        format!(
+            r#"{{"occurrence": "{occurrence}", "cause": "Deposit", "changes": [
+                {{"accountExternalId": "Dusk1", "type": "Cash", "size": 10,
+                 "securityDefinition": "None", "price": 1}}]}}"#
+        )
+    };
+    let events = [
+        "2022-09-25T10:00:00Z",
+        "2022-09-25T18:00:00Z",
+        "2022-09-26T12:00:00Z",
+        "2022-09-27T09:00:00Z",
+    ]
+    .map(deposit)
+    .join(",");
+    let json = format!(r#"{{"Dusk1": {{"events": [{events}]}}}}"#);
+    let time = |t: &str| t.parse().expect("valid time");
+    let batches = |options: &ParseOptions, by_day| {
+        let limits = BatchLimits {
+            by_day,
+            ..BatchLimits::default()
+        };
+        json_bytes(&json, options)
+            .expect("valid json")
+            .into_batches(&options.registry, &limits)
+            .expect("registered tickers")
+    };
+
+    let all = options();
+    assert_eq!(batches(&all, false).len(), 1);
+    let days: Vec<_> = batches(&all, true)
+        .iter()
+        .map(|batch| batch.transfers.len())
+        .collect();
+    assert_eq!(days, [2, 1, 1]);
+
+    let window = ParseOptions {
+        from: Some(time("2022-09-25T12:00:00Z")),
+        to: Some(time("2022-09-27T00:00:00Z")),
+        ..options()
+    };
+    let days: Vec<_> = batches(&window, true)
+        .iter()
+        .map(|batch| batch.transfers.len())
+        .collect();
+    assert_eq!(days, [1, 1]);
+}
@@ -0,0 +1,556 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+// Copyright (c) DUSK NETWORK. All rights reserved.
+
+use std::path::PathBuf;
+
+use canonical::EncodeToVec;
+use dusk_abi::ContractId;
+use dusk_bls12_381::BlsScalar;
+use dusk_bls12_381_sign::{PublicKey as BlsPublicKey, SecretKey};
+use dusk_bytes::Serializable;
+use dusk_wallet::WalletPath;
+use governance_core::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+const JSON: &str = r#"{"Dusk1": {"events": [
+    {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 1000.0,
+         "securityDefinition": "None", "price": 1}]},
+    {"occurrence": "2022-09-26T12:00:00Z", "cause": "Rebalance", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": -750.5,
+         "securityDefinition": "None", "price": 1},
+        {"accountExternalId": "Dusk1", "type": "Security", "size": 30.0,
+         "securityDefinition": "TSWE", "price": 25.0}]},
+    {"occurrence": "2022-09-27T12:00:00Z", "cause": "Fee", "changes": [
+        {"accountExternalId": "Dusk1", "type": "Cash", "size": 2.25,
+         "securityDefinition": "None", "price": 1}]}
+]}}"#;
+
+// keys derived from the account names, the inputs have no key registry
+fn options() -> ParseOptions {
+    ParseOptions {
+        derive_keys: true,
+        ..ParseOptions::default()
+    }
+}
+
+fn governance(confirmation_timeout: u64) -> Governance {
+    let wallet = SecureWallet {
+        path: WalletPath::from(PathBuf::from("wallet.dat")),
+        pwd: String::new(),
+    };
+    let config = Config {
+        confirmation_timeout,
+        poll_interval: 1,
+        ..Config::default()
+    };
+
+    Governance::from_config(wallet, config)
+}
+
+fn secret_key() -> SecretKey {
+    SecretKey::random(&mut StdRng::seed_from_u64(0xbeef))
+}
+
+#[tokio::test]
+async fn sends_every_batch() {
+    let node = MockNode::new();
+    node.script([TxStatus::NotFound, TxStatus::NotFound]);
+
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    let mut expected = data_batches();
+
+    let receipts = governance(100)
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect("all batches confirmed");
+
+    assert_eq!(receipts.len(), expected.len());
+    for receipt in &receipts {
+        assert_eq!(receipt.status, ReceiptStatus::Confirmed);
+        assert!(receipt.tx_hash.is_some());
+        assert!(receipt.block_height.is_some());
+    }
+
+    let mut executed: Vec<_> = node
+        .executed()
+        .into_iter()
+        .map(|(contract_id, (_, _, (seed, op, transfers)))| {
+            (contract_id, seed, op, transfers)
+        })
+        .collect();
+
+    // batches of different securities are sent in no particular order
+    executed.sort_by_key(|(id, _, op, _)| (id.as_bytes().to_vec(), *op));
+    expected.sort_by_key(|(id, _, op, _)| (id.as_bytes().to_vec(), *op));
+    assert_eq!(executed, expected);
+}
+
+#[test]
+fn dry_run_signs_without_sending() {
+    let dir = std::env::temp_dir()
+        .join(format!("governance-dry-run-{}", std::process::id()));
+    let mut governance = governance(100);
+    governance.with_journal(dir.join("journal.json"));
+    governance.with_sent_registry(dir.join("sent.json"));
+    governance.with_audit_log(dir.join("audit.log"));
+
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    let dry_run = governance
+        .dry_run_with(data, &secret_key())
+        .expect("payloads signed");
+
+    let pk = BlsPublicKey::from(&secret_key());
+    let mut expected = data_batches();
+    assert_eq!(dry_run.len(), expected.len());
+    for batch in &dry_run {
+        let verified =
+            verify_payload(&batch.payload, &pk).expect("valid signature");
+        assert_eq!(verified.transfers.len(), batch.count);
+        expected.retain(|(contract_id, seed, _, _)| {
+            (contract_id, seed) != (&batch.contract_id, &verified.seed)
+        });
+    }
+    assert!(expected.is_empty(), "every batch is signed");
+    assert!(!dir.exists(), "nothing is recorded");
+}
+
+#[tokio::test]
+async fn stops_on_transaction_error() {
+    let node = MockNode::new();
+    node.script([TxStatus::Error(String::from("Out of gas"))]);
+
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    let err = governance(100)
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect_err("the first batch fails");
+
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(GraphQLError::Transaction(_))
+    ));
+    assert_eq!(node.executed().len(), 1);
+
+    let Some(Receipts(receipts)) = err.downcast_ref() else {
+        panic!("the error carries the receipts");
+    };
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].status, ReceiptStatus::Failed);
+    assert_eq!(
+        receipts[0].error.as_deref(),
+        Some("Transaction error: Out of gas")
+    );
+}
+
+#[tokio::test]
+async fn times_out_unconfirmed_transactions() {
+    let node = MockNode::new();
+    node.script([TxStatus::NotFound]);
+
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    let err = governance(0)
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect_err("the first batch is never confirmed");
+
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(GraphQLError::Timeout(..))
+    ));
+}
+
+#[tokio::test]
+async fn pending_transactions_are_not_sent_again() {
+    let path = std::env::temp_dir()
+        .join(format!("governance-journal-{}.json", std::process::id()));
+    let data = || json_bytes(JSON, &options()).expect("valid json");
+    // every batch is pending, whichever is sent first
+    let batches = data()
+        .into_batches(&Registry::default(), &BatchLimits::default())
+        .expect("registered securities");
+    let mut journal = Journal::open(path.clone()).expect("empty journal");
+    for batch in &batches {
+        journal
+            .submitted(batch, "pending")
+            .expect("journal written");
+    }
+
+    let node = MockNode::new();
+    node.script([TxStatus::NotFound]);
+    let mut governance = governance(0);
+    governance.with_journal(path.clone());
+    let err = governance
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect_err("the pending transaction is never confirmed");
+
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(GraphQLError::Timeout(..))
+    ));
+    assert!(node.executed().is_empty());
+    let journal = Journal::open(path.clone()).expect("journal kept");
+    assert!(batches.iter().all(|batch| matches!(
+        journal.get(batch),
+        Some(entry) if entry.state == TxState::Submitted
+    )));
+
+    std::fs::remove_file(path).expect("journal written");
+}
+
+#[tokio::test]
+async fn retries_transient_errors() {
+    let node = MockNode::new();
+    node.fail(2, 2);
+
+    let retry = RetryPolicy {
+        max_attempts: 3,
+        base_delay: 1,
+        ..RetryPolicy::default()
+    };
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    let metrics = Metrics::new();
+    let mut governance = governance_with(retry.clone());
+    governance.with_metrics(metrics.clone());
+    governance
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect("the node recovers within the attempts");
+    let batches = data_batches().len();
+    assert_eq!(node.executed().len(), batches);
+
+    let rendered = metrics.render();
+    assert!(
+        rendered.contains("governance_retries_total{class=\"broadcast\"} 2")
+    );
+    assert!(rendered.contains("governance_retries_total{class=\"response\"} 2"));
+    assert!(rendered
+        .contains(&format!("governance_confirmation_seconds_count {batches}")));
+
+    let node = MockNode::new();
+    node.lose_replies(1);
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    governance_with(retry.clone())
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect("the unacknowledged transaction is confirmed");
+    assert_eq!(node.executed().len(), batches, "nothing is executed twice");
+
+    let node = MockNode::new();
+    node.fail(1, 0);
+    let retry = RetryPolicy {
+        retryable: vec![ErrorClass::Connection, ErrorClass::Response],
+        ..retry
+    };
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    governance_with(retry)
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect_err("broadcast errors are not retried");
+    assert!(node.executed().is_empty());
+}
+
+fn governance_with(retry: RetryPolicy) -> Governance {
+    let mut governance = governance(100);
+    governance.with_config(Config {
+        poll_interval: 1,
+        retry,
+        ..Config::default()
+    });
+    governance
+}
+
+#[tokio::test]
+async fn splits_batches_over_the_limit() {
+    let node = MockNode::new();
+    let data = json_bytes(JSON, &options()).expect("valid json");
+
+    let mut governance = governance(100);
+    governance.with_config(Config {
+        poll_interval: 1,
+        max_batch_transfers: Some(1),
+        ..Config::default()
+    });
+    governance
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect("all batches confirmed");
+
+    let executed = node.executed();
+    let transfers: usize = data_batches().iter().map(|b| b.3.len()).sum();
+    assert_eq!(executed.len(), transfers);
+
+    let mut seeds: Vec<_> = executed
+        .iter()
+        .map(|(_, (_, _, (seed, _, transfers)))| {
+            assert_eq!(transfers.len(), 1);
+            seed.to_bytes()
+        })
+        .collect();
+    seeds.sort();
+    seeds.dedup();
+    assert_eq!(seeds.len(), transfers, "every chunk has its own seed");
+
+    // the size limit covers the signature and length prefix too
+    let size = |(_, signed): &(ContractId, SignedPayload)| {
+        signed.encode_to_vec().len()
+    };
+    let limit = executed.iter().map(size).max().expect("executed payloads");
+    let limited = |max_batch_bytes| {
+        let mut limited = crate::governance(100);
+        limited.with_config(Config {
+            poll_interval: 1,
+            max_batch_bytes,
+            ..Config::default()
+        });
+        limited
+    };
+
+    let node = MockNode::new();
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    limited(Some(limit))
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect("all batches confirmed");
+    assert!(node.executed().iter().all(|e| size(e) <= limit));
+    assert_eq!(node.executed().len(), transfers);
+
+    let data = json_bytes(JSON, &options()).expect("valid json");
+    let err = limited(Some(limit - 1))
+        .send_with(data, &secret_key(), &node, &node)
+        .await
+        .expect_err("a transfer can't fit");
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(BatchError::TooLarge { max, .. }) if *max == limit - 1
+    ));
+}
+
+#[tokio::test]
+async fn identical_chunks_are_all_sent() {
+    // two equal payouts split in chunks of one transfer share the seed
+    let json = r#"{"Dusk1": {"events": [
+        {"occurrence": "2022-09-25T10:00:00Z", "cause": "Deposit", "changes": [
+            {"accountExternalId": "Dusk1", "type": "Cash", "size": 10.0,
+             "securityDefinition": "None", "price": 1},
+            {"accountExternalId": "Dusk1", "type": "Cash", "size": 10.0,
+             "securityDefinition": "None", "price": 1}]}
+    ]}}"#;
+    let dir = std::env::temp_dir()
+        .join(format!("governance-chunks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("temporary directory");
+    let run = |duplicates| {
+        let mut governance = governance(100);
+        governance.with_config(Config {
+            poll_interval: 1,
+            max_batch_transfers: Some(1),
+            duplicates,
+            ..Config::default()
+        });
+        governance.with_journal(dir.join("journal.json"));
+        governance.with_sent_registry(dir.join("sent.json"));
+        governance
+    };
+    let data = || json_bytes(json, &options()).expect("valid json");
+
+    let node = MockNode::new();
+    let receipts = run(DuplicatePolicy::Refuse)
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect("all batches confirmed");
+    assert_eq!(receipts.len(), 2);
+    assert!(receipts
+        .iter()
+        .all(|r| r.status == ReceiptStatus::Confirmed));
+    assert_eq!(node.executed().len(), 2);
+
+    // both were confirmed, and recognized as such
+    let receipts = run(DuplicatePolicy::Skip)
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect("duplicates are skipped");
+    assert!(receipts.iter().all(|r| r.status == ReceiptStatus::Skipped));
+    assert_eq!(node.executed().len(), 2);
+
+    std::fs::remove_dir_all(dir).expect("records written");
+}
+
+#[tokio::test]
+async fn confirmed_batches_are_not_sent_twice() {
+    let path = std::env::temp_dir()
+        .join(format!("governance-sent-{}.json", std::process::id()));
+    let run = |duplicates, max_batch_transfers| {
+        let mut governance = governance(100);
+        governance.with_config(Config {
+            poll_interval: 1,
+            duplicates,
+            max_batch_transfers,
+            ..Config::default()
+        });
+        governance.with_sent_registry(path.clone());
+        governance
+    };
+    let data = || json_bytes(JSON, &options()).expect("valid json");
+
+    let node = MockNode::new();
+    run(DuplicatePolicy::Refuse, None)
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect("all batches confirmed");
+    let sent = node.executed().len();
+
+    let err = run(DuplicatePolicy::Refuse, None)
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect_err("the input was already sent");
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(DuplicateError(duplicates)) if duplicates.len() == sent
+    ));
+    assert_eq!(node.executed().len(), sent);
+
+    let receipts = run(DuplicatePolicy::Skip, None)
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect("duplicates are skipped");
+    assert!(receipts.iter().all(|r| r.status == ReceiptStatus::Skipped));
+    assert_eq!(node.executed().len(), sent);
+
+    // the transfers are recognized in batches split differently
+    let err = run(DuplicatePolicy::Refuse, Some(1))
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect_err("the transfers were already sent");
+    assert!(err.downcast_ref::<DuplicateError>().is_some());
+    let receipts = run(DuplicatePolicy::Skip, Some(1))
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect("duplicates are skipped");
+    assert!(receipts.len() > sent);
+    assert!(receipts.iter().all(|r| r.status == ReceiptStatus::Skipped));
+    assert_eq!(node.executed().len(), sent);
+
+    run(DuplicatePolicy::Resend, None)
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect("duplicates are sent again");
+    assert_eq!(node.executed().len(), 2 * sent);
+
+    // the transfers are recognized with other timestamps, as set by `--now`
+    let registry = SentRegistry::open(path.clone()).expect("registry written");
+    let now = ParseOptions {
+        timestamp: Some(1),
+        ..options()
+    };
+    let batches = json_bytes(JSON, &now)
+        .expect("valid json")
+        .into_batches(&now.registry, &BatchLimits::default())
+        .expect("registered securities");
+    assert!(batches.iter().all(|batch| registry.get(batch).is_none()));
+    assert!(batches.iter().all(|b| registry.resembles(b).is_some()));
+
+    std::fs::remove_file(path).expect("registry written");
+}
+
+#[tokio::test]
+async fn broadcasts_are_chained_in_the_audit_log() {
+    let path = std::env::temp_dir()
+        .join(format!("governance-audit-{}.log", std::process::id()));
+    let sent = std::env::temp_dir()
+        .join(format!("governance-audited-{}.json", std::process::id()));
+    let run = || {
+        let mut governance = governance(100);
+        governance.with_config(Config {
+            poll_interval: 1,
+            duplicates: DuplicatePolicy::Resend,
+            ..Config::default()
+        });
+        governance.with_audit_log(path.clone());
+        governance.with_sent_registry(sent.clone());
+        governance
+    };
+    let data = || json_bytes(JSON, &options()).expect("valid json");
+
+    let node = MockNode::new();
+    for _ in 0..2 {
+        run()
+            .send_with(data(), &secret_key(), &node, &node)
+            .await
+            .expect("all batches confirmed");
+    }
+
+    let registry = SentRegistry::open(sent.clone()).expect("registry written");
+    let head = registry.audit_head();
+    let entries = AuditLog::verify(&path, &registry).expect("intact chain");
+    assert_eq!(entries.len(), node.executed().len());
+    assert_eq!(head, entries.last().map(|e| e.hash.as_str()));
+    let pk = BlsPublicKey::from(&secret_key());
+    let signer = bs58::encode(pk.to_bytes()).into_string();
+    assert!(entries.iter().all(|e| e.signer.as_ref() == Some(&signer)));
+    assert!(entries.iter().all(|e| e.signature.is_some()));
+
+    // the signatures cover the hash of their own entry
+    let content = std::fs::read_to_string(&path).expect("log written");
+    let (first, second) = (&entries[0], &entries[1]);
+    let signature = |e: &AuditEntry| e.signature.clone().expect("signed");
+    let edited = content.replacen(&signature(second), &signature(first), 1);
+    std::fs::write(&path, edited).expect("log written");
+    assert!(matches!(
+        AuditLog::verify(&path, &SentRegistry::default()),
+        Err(AuditError::Signature(2))
+    ));
+
+    let edited = content.replacen(&entries[1].tx_hash, "00", 1);
+    std::fs::write(&path, edited).expect("log written");
+    assert!(matches!(
+        AuditLog::verify(&path, &SentRegistry::default()),
+        Err(AuditError::Broken(2))
+    ));
+
+    let err = run()
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect_err("an altered log is not extended");
+    assert!(matches!(err.downcast_ref(), Some(AuditError::Broken(2))));
+
+    // a removed log would verify on its own, the registry keeps its head
+    std::fs::remove_file(&path).expect("log written");
+    let err = run()
+        .send_with(data(), &secret_key(), &node, &node)
+        .await
+        .expect_err("a removed log is not started again");
+    assert!(matches!(err.downcast_ref(), Some(AuditError::Truncated(_))));
+
+    // nor once the registry lost the head too
+    let mut registry: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&sent).expect("registry"))
+            .expect("valid registry");
+    let fields = registry.as_object_mut().expect("registry object");
+    fields.remove("audit_head").expect("head recorded");
+    std::fs::write(&sent, registry.to_string()).expect("registry written");
+    let registry = SentRegistry::open(sent.clone()).expect("valid registry");
+    assert!(matches!(
+        AuditLog::verify(&path, &registry),
+        Err(AuditError::Missing)
+    ));
+
+    std::fs::remove_file(sent).expect("registry written");
+}
+
+// contract id, seed, op code and transfers of every batch in the input
+fn data_batches() -> Vec<(ContractId, BlsScalar, u8, Vec<Transfer>)> {
+    json_bytes(JSON, &options())
+        .expect("valid json")
+        .into_batches(&Registry::default(), &BatchLimits::default())
+        .expect("registered securities")
+        .into_iter()
+        .map(|batch| {
+            let op = batch.kind.op();
+            (batch.contract_id, batch.seed(), op, batch.transfers)
+        })
+        .collect()
+}